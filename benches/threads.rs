@@ -0,0 +1,45 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rfind::main::searchbuilder::SearchBuilder;
+use tempfile::TempDir;
+
+// 32 directories with 32 directories each, which hold 16 files each: 17441 entries in all
+const WIDTH: usize = 32;
+const FILES: usize = 16;
+
+fn create_tree(root: &Path) -> std::io::Result<u64> {
+    for outer in 0..WIDTH {
+        for inner in 0..WIDTH {
+            let directory = root.join(format!("{}/{}", outer, inner));
+            fs::create_dir_all(&directory)?;
+            for file in 0..FILES {
+                fs::write(directory.join(format!("{}.txt", file)), "")?;
+            }
+        }
+    }
+    Ok((1 + WIDTH + WIDTH * WIDTH + WIDTH * WIDTH * FILES) as u64)
+}
+
+// The entries found per second for 1, 2, 4, ... threads, up to the number of CPUs. The search
+// scales linearly when the throughput doubles with the threads.
+fn search_with_more_threads(c: &mut Criterion) {
+    let temp = TempDir::new().unwrap();
+    let entries = create_tree(temp.path()).unwrap();
+    let cpus = thread::available_parallelism().map(|cpus| cpus.get()).unwrap_or(1);
+
+    let mut group = c.benchmark_group("threads");
+    group.throughput(Throughput::Elements(entries));
+    let counts = (0..).map(|power| 1 << power).take_while(|threads| *threads <= cpus);
+    for threads in counts {
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, &threads| {
+            b.iter(|| assert_eq!(SearchBuilder::new(temp.path()).threads(threads).build().unwrap().count() as u64, entries));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, search_with_more_threads);
+criterion_main!(benches);
@@ -6,12 +6,14 @@ use std::mem::{discriminant, Discriminant};
 use std::io::{Write, Read, Seek, SeekFrom};
 use std::fs::{self, FileType, ReadDir};
 use std::os::unix::fs::FileTypeExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::env;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
 use clap::{arg, crate_authors, crate_version, value_parser, Arg, ArgAction, ArgMatches, Command, ValueEnum};
 use libc::write;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::fs::File;
 use speculoos::prelude::*;
 use test_case::test_case;
@@ -27,13 +29,27 @@ use rfind::main::symlinksetting::SymLinkSetting;
 use rfind::main::params::Params;
 use rfind::main::searcher::Searcher;
 use rfind::main::test::Test;
+use rfind::main::expression::Registry;
 use rfind::main::debugopts::DebugOpts;
 use rfind::main::threadpool::ThreadPool;
+use rfind::main::action::Action;
+use rfind::main::sink::Sink;
+use rfind::main::outputfiles::OutputFiles;
+use rfind::main::printf::Printf;
+use rfind::main::filedescriptor::FileDescriptor;
+use rfind::main::outputformat::OutputFormat;
+use rfind::main::jsonlogger::JsonLogger;
+use rfind::main::csvlogger::CsvLogger;
+use rfind::main::field::Field;
+use rfind::main::order::{Order, SortBy};
+use rfind::main::stop::{Stop, StopReason};
+
+// the number of lines that can be waiting for the output thread before the search has to wait for it
+const OUTPUT_CAPACITY: usize = 1024;
+// the tokens of the expression that look at the entries, and that run the search
+const TESTS: [&str; 3] = ["--name", "--type", "--regex"];
 
 fn main() {
-    let logger = StandardLogger::new();
-
-
     let mut matches: ArgMatches = Command::new("MyApp")
         .version(crate_version!())
         .author(crate_authors!("\n"))
@@ -62,20 +78,27 @@ fn main() {
             .action(ArgAction::Set)
             .help("Set optimisation level")
         )
+        .arg(Arg::new("threads")
+            .short('j')
+            .value_parser(value_parser!(u64).range(1..))
+            .action(ArgAction::Set)
+            .help("The number of threads to search with. -j 1 searches on the main thread.
+            Defaults to RFIND_THREADS when it is set, otherwise to the number of available CPUs")
+        )
         .arg(Arg::new("max_depth")
             .value_parser(value_parser!(u32))
             .long("maxdepth")
             .action(ArgAction::Set)
             .help("Descend at most the provided number of levels, this value must be a non-negative integer.
-            Using max depth of 0 will apply the expression 
-            for files only in the current directory, and will not search subdirectories")
+            The starting points are at level 0, so using max depth of 0 will only apply the expression
+            to the starting points. It can also be given in the expression")
         )
         .arg(Arg::new("min_depth")
             .value_parser(value_parser!(u32))
             .long("mindepth")
             .action(ArgAction::Set)
             .help("Do not apply any tests or actions at levels less than levels (a  non-negative  integer).
-              Using -mindepth 1 means process all files except the starting-points."))
+              Using -mindepth 1 means process all files except the starting-points. It can also be given in the expression"))
         .arg(Arg::new("name")
             .long("name")
             .help("The name of the file to find")
@@ -86,16 +109,96 @@ fn main() {
         )
         .arg(Arg::new("regex")
             .long("regex")
-            .help("a regular expression to evaluate against. Will evaluate against the absolute path.")
+            .help("A regular expression that the whole path of the file must match, e.g. '.*/src/.*\\.rs' rather than 'src'")
+        )
+        .arg(Arg::new("format")
+            .long("format")
+            .value_parser(["text", "json", "csv", "tsv"])
+            .default_value("text")
+            .help("The format to write matches in. json writes one object per line, with an object for every error as well")
+        )
+        .arg(Arg::new("columns")
+            .long("columns")
+            .default_value("path")
+            .help("The comma separated columns to write with --format csv or tsv, e.g. path,size,mtime,owner.
+            Columns have the same names as the fields of --printf, and its directive letters can be used as well")
+        )
+        .arg(Arg::new("header")
+            .long("header")
+            .action(ArgAction::SetTrue)
+            .help("Write a header row at the top of --format csv or tsv, even when nothing matches")
+        )
+        .arg(Arg::new("sort")
+            .long("sort")
+            .value_parser(["name", "path", "size", "mtime"])
+            .conflicts_with("stable")
+            .help("Write the matches sorted by name, path, size or mtime once the search has finished")
+        )
+        .arg(Arg::new("reverse")
+            .long("reverse")
+            .action(ArgAction::SetTrue)
+            .requires("sort")
+            .help("Reverse the order of --sort")
+        )
+        .arg(Arg::new("stable")
+            .long("stable")
+            .action(ArgAction::SetTrue)
+            .help("Write the matches in the order that a single threaded search would find them in, while still searching with every thread")
+        )
+        .arg(Arg::new("ignore_readdir_race")
+            .long("ignore_readdir_race")
+            .action(ArgAction::SetTrue)
+            .overrides_with("noignore_readdir_race")
+            .help("Do not fail the search because of files that were removed while it was running. They are still warned about")
+        )
+        .arg(Arg::new("noignore_readdir_race")
+            .long("noignore_readdir_race")
+            .action(ArgAction::SetTrue)
+            .overrides_with("ignore_readdir_race")
+            .help("Exit with a status of 1 when files are removed while the search is running, which is the default")
+        )
+        .arg(Arg::new("timeout")
+            .long("timeout")
+            .value_parser(parse_duration)
+            .help("Stop searching after the given time, e.g. 30s, 500ms, 5m or 1h, and write what was found until then")
+        )
+        .arg(Arg::new("bfs")
+            .long("bfs")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("stable")
+            .help("Search the directories of each level before those of the next one, so that the shallowest matches are found first.
+            Once too many directories are waiting for the next level, further ones are searched depth-first")
+        )
+        .arg(Arg::new("max_results")
+            .long("max-results")
+            .value_parser(value_parser!(u64).range(1..))
+            .help("Stop searching once this many files have matched")
+        )
+        .arg(Arg::new("exists")
+            .long("exists")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("max_results")
+            .help("Write nothing, and exit with 0 if any file matches and with 1 otherwise. Stops at the first match")
+        )
+        .arg(Arg::new("error_summary")
+            .long("error-summary")
+            .action(ArgAction::SetTrue)
+            .help("Write the number of errors of each kind to stderr once the search has finished")
+        )
+        .arg(Arg::new("files0_from")
+            .long("files0-from")
+            .value_parser(value_parser!(PathBuf))
+            .help("Read the starting points from a file, or from stdin when the file is -. Each starting point ends with a NUL,
+            as written by --print0 or git ls-files -z. Starting points can not be given on the command line as well")
         )
-        .allow_missing_positional(true)
         .arg(Arg::new("starting_path")
-            .default_value(".")
-            .value_parser(value_parser!(String))
+            .num_args(0..)
+            .value_parser(value_parser!(PathBuf))
         )
         .arg(Arg::new("expression")
             .default_value("--true")
             .num_args(0..)
+            .last(true)
             .value_parser(value_parser!(String))
         )
         .get_matches();
@@ -126,22 +229,41 @@ fn main() {
         _ => None
     };
 
-    let params = Params {
-        symlink_setting,
-        debug_opts,
-        optimisation_level
-    };
+    // the search stops at the next entry, so that what was found is still written
+    Stop::catch_signals();
 
+    let timeout = matches.remove_one::<Duration>("timeout");
 
+    let exists = matches.get_flag("exists");
+    let max_results = match exists {
+        true => Some(1),
+        false => matches.remove_one::<u64>("max_results")
+    };
 
+    let threads = thread_count(matches.remove_one::<u64>("threads"), env::var("RFIND_THREADS").ok());
 
     let max_depth = matches.remove_one::<u32>("max_depth");
 
     let min_depth = matches.remove_one::<u32>("min_depth");
 
-    let starting_path = matches.remove_one::<String>("starting_path");
+    let starting_paths: Vec<PathBuf> = matches.remove_many::<PathBuf>("starting_path").map(|paths| paths.collect()).unwrap_or_default();
+    let starting_paths = match matches.remove_one::<PathBuf>("files0_from") {
+        Some(_) if !starting_paths.is_empty() => {
+            eprintln!("rfind: starting points can not be given on the command line together with --files0-from");
+            exit(1);
+        }
+        Some(files0_from) => match read_starting_paths(&files0_from) {
+            Ok(starting_paths) => starting_paths,
+            Err(error) => {
+                eprintln!("rfind: {}", error);
+                exit(1);
+            }
+        },
+        None if starting_paths.is_empty() => vec![PathBuf::from(".")],
+        None => starting_paths
+    };
     
-    let expression = match matches.remove_many::<String>("expression") {
+    let mut expression = match matches.remove_many::<String>("expression") {
         Some(expression) => {
             let mut a: Vec<String> = Vec::new();
             for token in expression {
@@ -151,117 +273,294 @@ fn main() {
         }
         _ => vec!["--true".to_string()]
     };
-    
-    let threadpool = ThreadPool::new(4);
-    let searcher = Searcher::new(params, max_depth, min_depth, starting_path.unwrap_or(format!(".")), Some(Arc::new(Mutex::new(threadpool))));
-    println!("{:#?}", searcher);
-    eval(expression, Arc::new(searcher));
-}
 
+    let (max_depth, min_depth) = match take_depth_options(&mut expression) {
+        Ok((expression_max_depth, expression_min_depth)) => (expression_max_depth.or(max_depth), expression_min_depth.or(min_depth)),
+        Err(error) => {
+            eprintln!("rfind: {}", error);
+            exit(1);
+        }
+    };
+    if expression.is_empty() {
+        expression.push("--true".to_string());
+    }
 
-struct Expression {
-    expression_str: Option<Box<Vec<String>>>,
-    sub_expression: Option<Box<Vec<Expression>>>
-}
+    // kept open until the search has finished logging
+    let mut output_files = OutputFiles::new();
+    // checked before the search starts, so that e.g. a regular expression that does not compile is
+    // an error rather than matching nothing
+    let test = match Registry::new().parse(&expression, optimisation_level) {
+        Ok(predicate) => Test::Custom(predicate),
+        Err(error) => {
+            eprintln!("rfind: {}", error);
+            exit(1);
+        }
+    };
 
-fn some_test_returns_true(input: Vec<String>) -> bool {
-    _ = input;
-    true
-}
+    // checked before the actions are parsed, which creates the files that --fprint and the like write to
+    if let Some(action) = expression.iter().find(|token| is_action(token)).filter(|_| exists) {
+        eprintln!("rfind: --exists writes nothing and changes nothing, so it can not be used with the {} action", action);
+        exit(1);
+    }
 
-fn some_test_returns_false(input: Vec<String>) -> bool {
-    _ = input;
-    false
-}
+    let actions = match parse_actions(&expression, &mut output_files) {
+        Ok(actions) => actions,
+        Err(error) => {
+            eprintln!("rfind: {}", error);
+            exit(1);
+        }
+    };
+
+    // only the exit status says whether anything matched
+    let actions = match exists {
+        true => Vec::new(),
+        false => actions
+    };
 
-fn eval(tokens: Vec<String>, searcher: Arc<Searcher>) -> bool {
-    println!("TOKENS: {:#?}", tokens);
+    let breadth_first = matches.get_flag("bfs");
+    if actions.contains(&Action::Delete) && breadth_first {
+        eprintln!("rfind: The --delete action needs a depth-first traversal, so it can not be used with --bfs.");
+        exit(1);
+    }
 
-    let iter = tokens.iter();
+    if actions.contains(&Action::Delete) && expression.iter().any(|token| token == "--prune") {
+        eprintln!("rfind: The --delete action automatically turns on depth-first traversal, so --prune would have no effect. Refusing to run.");
+        exit(1);
+    }
 
-    let mut ex = Expression {
-        expression_str: Some(Box::new(tokens.clone())),
-        sub_expression: None
+    let output_format = match matches.remove_one::<String>("format").as_deref() {
+        Some("json") => OutputFormat::Json,
+        Some("csv") => OutputFormat::Csv,
+        Some("tsv") => OutputFormat::Tsv,
+        _ => OutputFormat::Standard
     };
 
-    for (i, el) in iter.clone().enumerate() {
-        if el == ")" {
-            panic!(") should not be here!");
+    let mut columns = Vec::new();
+    for column in matches.remove_one::<String>("columns").unwrap_or_default().split(',') {
+        match Field::from_name(column.trim()) {
+            Some(field) => columns.push(field),
+            None => {
+                eprintln!("rfind: unknown column {}", column);
+                exit(1);
+            }
         }
-        if el == "(" {
-            let iter2 = tokens[i+1..].iter();
-            for (i2, el2) in iter2.enumerate() {
-                if el2 == ")" {
-                    return eval(tokens[i+1..i2-1].to_vec(), searcher);
-                }
-            }            
-            panic!("Could not find enclosing )");
+    }
+    let header = matches.get_flag("header");
+
+    let error_summary = matches.get_flag("error_summary");
+    let order = match matches.remove_one::<String>("sort").and_then(|sort| SortBy::from_name(&sort)) {
+        Some(sort_by) => Order::Sorted(sort_by, matches.get_flag("reverse")),
+        None if matches.get_flag("stable") => Order::Stable,
+        None => Order::Traversal
+    };
+
+    let params = Params {
+        symlink_setting,
+        output_format,
+        debug_opts,
+        optimisation_level,
+        depth_first: actions.contains(&Action::Delete),
+        breadth_first,
+        actions,
+        ignore_readdir_race: matches.get_flag("ignore_readdir_race")
+    };
+
+    // what the columns and the sort key are made from, which is fetched along with what the actions write
+    let column_mask = match output_format {
+        OutputFormat::Csv | OutputFormat::Tsv => columns.iter().fold(0, |mask, column| mask | column.stat_mask()),
+        _ => 0
+    };
+    let stat_mask = match order {
+        Order::Sorted(sort_by, _) => column_mask | sort_by.stat_mask(),
+        _ => column_mask
+    };
 
+    let logger: Box<dyn Logger + Send> = match output_format {
+        OutputFormat::Json => Box::new(JsonLogger::new()),
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let separator = match output_format {
+                OutputFormat::Csv => ',',
+                _ => '\t'
+            };
+            let logger = CsvLogger::new(separator, columns);
+            // the command line has been checked by now, and --exists writes nothing at all
+            if header && !exists {
+                logger.write_header();
+            }
+            Box::new(logger)
         }
+        OutputFormat::Standard => Box::new(StandardLogger::new())
+    };
+
+    // with a single thread the search recurses on the main thread
+    let threadpool = (threads > 1).then(|| Arc::new(ThreadPool::new(threads)));
+    let searcher = Searcher::with_starting_paths(params, max_depth, min_depth, starting_paths, threadpool.clone()).with_stat_mask(stat_mask);
+    let searcher = match timeout {
+        Some(timeout) => searcher.with_timeout(timeout),
+        None => searcher
+    };
+    let searcher = match max_results {
+        Some(max_results) => searcher.with_max_results(max_results),
+        None => searcher
+    };
+    let searcher = Arc::new(searcher);
+    let (sink, output_thread) = Sink::with_order(OUTPUT_CAPACITY, logger, order);
+    Arc::clone(&searcher).search(test, &sink);
+    // a job that panicked has already written why to stderr
+    let panicked = threadpool.is_some_and(|threadpool| {
+        threadpool.wait();
+        threadpool.panicked() > 0
+    });
+    drop(sink);
+    output_thread.join().unwrap();
+    if error_summary {
+        eprintln!("{}", searcher.errors());
+    }
+    if searcher.stop().reason() == Some(StopReason::Interrupted) {
+        exit(130);
     }
+    if exists {
+        exit(if searcher.match_count() > 0 { 0 } else { 1 });
+    }
+    if searcher.stop().reason() == Some(StopReason::TimedOut) {
+        eprintln!("rfind: the search timed out after {:?}, so not every file was searched", timeout.unwrap_or_default());
+        exit(1);
+    }
+    if searcher.has_failed() || panicked {
+        exit(1);
+    }
+}
 
-    let mut expression_result: bool = false;
-    let searcher_clone = Arc::clone(&searcher);
-    let directory_path = Path::new(&searcher_clone.starting_path);
-    for (i, el) in iter.enumerate() {
-        if el == "--true" {
-            expression_result = true;
-        }
-        if el == "--false" {
-            expression_result = false;
-        }
-        if el == "--or" {
-            if expression_result {
-                return true;
-            } else {
-                return expression_result || eval(tokens[i+1..].to_vec(), searcher);
-            }
+// Starting points are separated by NULs, and the last one may or may not be followed by one.
+fn read_starting_paths(files0_from: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut contents = Vec::new();
+    let result = match files0_from.as_os_str() == "-" {
+        true => io::stdin().lock().read_to_end(&mut contents),
+        false => File::open(files0_from).and_then(|mut file| file.read_to_end(&mut contents))
+    };
+    result.map_err(|error| format!("cannot read {}: {}", files0_from.to_string_lossy(), error))?;
+    if contents.last() == Some(&0) {
+        contents.pop();
+    }
+    if contents.is_empty() {
+        return Ok(Vec::new());
+    }
+    contents.split(|byte| *byte == 0).map(|starting_path| match starting_path.is_empty() {
+        true => Err(format!("{}: invalid zero-length file name", files0_from.to_string_lossy())),
+        false => Ok(PathBuf::from(OsStr::from_bytes(starting_path)))
+    }).collect()
+}
+
+// A number followed by ms, s, m or h. A number on its own is seconds.
+fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let split = duration.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(duration.len());
+    let (number, unit) = duration.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("{} is not a duration, e.g. 30s", duration))?;
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(format!("unknown unit {} in {}, expected ms, s, m or h", unit, duration))
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("{} is too long for a duration", duration))
+}
+
+// -j takes precedence over RFIND_THREADS, which takes precedence over the number of available CPUs.
+fn thread_count(threads: Option<u64>, threads_from_env: Option<String>) -> usize {
+    if let Some(threads) = threads {
+        return threads as usize;
+    }
+    if let Some(threads_from_env) = threads_from_env {
+        match threads_from_env.trim().parse::<usize>() {
+            Ok(threads) if threads > 0 => return threads,
+            _ => eprintln!("rfind: ignoring RFIND_THREADS={}, it must be a positive integer", threads_from_env)
         }
-        if el == "--and" {
-            if !expression_result {
-                return false;
-            } else {
-                return expression_result && eval(tokens[i+1..].to_vec(), searcher);
+    }
+    std::thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1)
+}
+
+// --maxdepth and --mindepth are global options, which apply to the whole expression wherever they are
+// given, so they are taken out of it before it is evaluated. As in find, giving one after a test is
+// allowed, with a warning.
+fn take_depth_options(expression: &mut Vec<String>) -> Result<(Option<u32>, Option<u32>), String> {
+    let (mut max_depth, mut min_depth) = (None, None);
+    let mut remaining = Vec::with_capacity(expression.len());
+    let mut first_test: Option<String> = None;
+    let mut tokens = std::mem::take(expression).into_iter();
+    while let Some(token) = tokens.next() {
+        let depth = match token.as_str() {
+            "--maxdepth" => &mut max_depth,
+            "--mindepth" => &mut min_depth,
+            _ => {
+                if first_test.is_none() && TESTS.contains(&token.as_str()) {
+                    first_test = Some(token.clone());
+                }
+                // the arguments of tests and actions are kept as they are, even if one is --maxdepth
+                let arguments = match token.as_str() {
+                    "--fprintf" => 2,
+                    "--name" | "--type" | "--regex" | "--printf" | "--fprint" | "--fprint0" | "--fls" => 1,
+                    _ => 0
+                };
+                remaining.push(token);
+                remaining.extend(tokens.by_ref().take(arguments));
+                continue;
             }
+        };
+        let value = tokens.next().ok_or(format!("{} expects a non-negative integer, but found nothing", token))?;
+        *depth = Some(value.parse::<u32>().map_err(|_| format!("{} expects a non-negative integer, but found {}", token, value))?);
+        if let Some(test) = &first_test {
+            eprintln!("rfind: warning: you have specified the global option {} after the argument {}, but global options are not positional, \
+                i.e., {} affects tests specified before it as well as those specified after it. Please specify global options before other arguments.", token, test, token);
         }
-        if el == "--not" {
-            return !eval(tokens[i+1..].to_vec(), searcher);
-        }
-        // tests logic
-        let lines = Box::new(Vec::new());
-        if el == "--name" { // todo maybe make one if statement for all tests?
-            let name: String = tokens.get(i + 1).expect("--name expects a file name, but found nothing").clone();
-            
-            ex.expression_str = Some(Box::new(vec![el.to_string(), name.clone()]));
-            let test = Test::Name(name.clone());
-            Arc::clone(&searcher).search_directory_path(directory_path, test, None, None, Box::clone(&lines));
-            expression_result = some_test_returns_true(*ex.expression_str.unwrap());
-        }
-        else if el == "--type" {
-            let r#type: String = tokens.get(i + 1).expect("--type expects a file type, but found nothing").clone();
-            
-            ex.expression_str = Some(Box::new(vec![el.to_string(), r#type.clone()]));
-            let test = Test::Types(r#type);
-            Arc::clone(&searcher).search_directory_path(directory_path, test, None, None, Box::clone(&lines));
-            expression_result = some_test_returns_true(*ex.expression_str.unwrap());
-        }
-        else if el == "--regex" {
-            let regex: String = tokens.get(i + 1).expect("--regex expects a regular expression, but found nothing").clone();
+    }
+    *expression = remaining;
+    Ok((max_depth, min_depth))
+}
+
+// Actions run on every file that the expression matches. When none are given the matches are printed.
+// Files named by actions are opened here, before the search starts.
+fn is_action(token: &str) -> bool {
+    matches!(token, "--print" | "--print0" | "--printf" | "--fprint" | "--fprint0" | "--fprintf" | "--delete" | "--ls" | "--fls")
+}
 
-            ex.expression_str = Some(Box::new(vec![el.to_string(), regex.clone()]));
-            let test = Test::Regex(regex);
-            Arc::clone(&searcher).search_directory_path(directory_path, test, None, None, Box::clone(&lines));
-            expression_result = some_test_returns_true(*ex.expression_str.unwrap());
+fn parse_actions(tokens: &[String], output_files: &mut OutputFiles) -> Result<Vec<Action>, String> {
+    let mut actions = Vec::new();
+    let mut iter = tokens.iter();
+    let mut open = |token: &String, path: Option<&String>| -> Result<FileDescriptor, String> {
+        let path = path.ok_or(format!("{} expects a file name, but found nothing", token))?;
+        output_files.open(Path::new(path)).map_err(|error| format!("{}: {}", path, error))
+    };
+    while let Some(token) = iter.next() {
+        match token.as_str() {
+            "--print" => actions.push(Action::Print(FileDescriptor::StdOut)),
+            "--print0" => actions.push(Action::Print0(FileDescriptor::StdOut)),
+            "--printf" => {
+                let format = iter.next().ok_or("--printf expects a format, but found nothing")?;
+                actions.push(Action::Printf(FileDescriptor::StdOut, Printf::parse(format)?));
+            }
+            "--fprint" => actions.push(Action::Print(open(token, iter.next())?)),
+            "--fprint0" => actions.push(Action::Print0(open(token, iter.next())?)),
+            "--fprintf" => {
+                let fd = open(token, iter.next())?;
+                let format = iter.next().ok_or("--fprintf expects a format, but found nothing")?;
+                actions.push(Action::Printf(fd, Printf::parse(format)?));
+            }
+            "--delete" => actions.push(Action::Delete),
+            "--ls" => actions.push(Action::Ls(FileDescriptor::StdOut)),
+            "--fls" => actions.push(Action::Ls(open(token, iter.next())?)),
+            _ => {}
         }
     }
-    expression_result
+    if actions.is_empty() {
+        actions.push(Action::Print(FileDescriptor::StdOut));
+    }
+    Ok(actions)
 }
 
+
 #[cfg(test)]
 use mockall::{automock, mock, predicate::*};
-use tempfile::Builder;
-use tempfile::TempDir;
-use tempfile::NamedTempFile;
 
 
 
@@ -272,275 +571,268 @@ mod tests {
     use self::test_case;
 
     use rfind::main::testlogger::TestLogger;
+    use rfind::main::jsonlogger::{json_bytes, json_string};
+    use rfind::main::order::Order;
+    use rfind::main::directory::{Directory, DirectoryEntries, DirectoryEntry};
+    use rfind::main::filesystem::{FileSystem, MemoryFileSystem, Os};
+    use rfind::main::entry::{Entry, Stat, STAT_ALL};
+    use rfind::main::errorcounts::ErrorCategory;
+    use rfind::main::filetype::FileType;
     use rfind::main::filedescriptor::FileDescriptor;
+    use rfind::main::searchbuilder::SearchBuilder;
+    use rfind::main::predicate::{All, Cost, Name, Predicate};
+    use std::fmt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Runs the search to the end, and gives back the searcher, to check how the search went, and
+    // everything that it logged.
+    fn search_to_end(searcher: Searcher, test: Test) -> (Arc<Searcher>, TestLogger) {
+        let searcher = Arc::new(searcher);
+        let (sink, output_thread) = Sink::new(OUTPUT_CAPACITY, TestLogger::new());
+        Arc::clone(&searcher).search(test, &sink);
+        drop(sink);
+        (searcher, output_thread.join().unwrap())
+    }
+
+    // The paths that were written to stdout, sorted, as the order depends on the file system.
+    fn stdout_paths(logs: &TestLogger) -> Vec<String> {
+        let mut paths: Vec<String> = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut).iter()
+            .map(|log| log.message.get_contained_message().into_owned())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    // A file system in memory that calls `hook` with the path of everything that is stat'ed, and
+    // fails the stat with the error that it gives, if any.
+    struct Hooked<F: Fn(&Path) -> io::Result<()> + Send + Sync>(MemoryFileSystem, F);
+
+    impl<F: Fn(&Path) -> io::Result<()> + Send + Sync> fmt::Debug for Hooked<F> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("Hooked").field(&self.0).finish()
+        }
+    }
+
+    impl<F: Fn(&Path) -> io::Result<()> + Send + Sync + 'static> FileSystem for Hooked<F> {
+        fn open_directory(self: Arc<Self>, path: &Path, follow_symlink: bool) -> io::Result<Directory> {
+            Directory::open_in(self, path, follow_symlink)
+        }
+
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<DirectoryEntry>> {
+            self.0.read_dir(path)
+        }
+
+        fn metadata(&self, path: &Path, follow_symlink: bool) -> io::Result<Stat> {
+            (self.1)(path)?;
+            self.0.metadata(path, follow_symlink)
+        }
+
+        fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+            self.0.read_link(path)
+        }
+
+        fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+            self.0.open(path)
+        }
+    }
 
     #[test]
     fn find_file_in_same_directory() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir()); 
+        const FILE_NAME_WITH_EXTENSION: &str = "find_file_in_same_directory.txt";
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tmp/find_file_in_same_directory.txt", ""));
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
-        let searcher = Searcher::new(params, None, None, tempfile::env::temp_dir().to_str().unwrap().to_string(), None);
-
-        // Create a file inside of `env::temp_dir()`.
-        let file = NamedTempFile::new()?;
-        let file_name_with_extension = file.path().file_name().unwrap().to_str().unwrap().to_string();
-        let test_by_name = Test::Name(file_name_with_extension.clone());
-
-        // Actlogger.clone(), 
-        //logger.clone(), 
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(tempfile::env::temp_dir().as_path(), test_by_name, None, None, Box::clone(&lines));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
+        let searcher = Searcher::new(params, None, None, "/tmp".to_string(), None).with_file_system(file_system);
+        let test_by_name = Test::Name(FILE_NAME_WITH_EXTENSION.to_string());
+
+        // Act
+        let (_, logs) = search_to_end(searcher, test_by_name);
 
-        // Assert  
-        let logs = logger.lock().unwrap();
+        // Assert
         let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
-        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), file.path().to_str().unwrap().to_string()),
-            "{}", format!("expected to find {} in logs, but the string could not be found. Full logs: \n{:#?}", file_name_with_extension, stdout_logs));
+        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), "/tmp/find_file_in_same_directory.txt".to_string()),
+            "{}", format!("expected to find {} in logs, but the string could not be found. Full logs: \n{:#?}", FILE_NAME_WITH_EXTENSION, stdout_logs));
 
         // Teardown
-        drop(file);
         Ok(())
     }
 
     #[test]
     fn find_file_in_child_directory() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        const FILE_NAME_WITH_EXTENSION: &'static str = "find_file_in_child_directory.txt";
-        assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
+        const FILE_NAME_WITH_EXTENSION: &str = "find_file_in_child_directory.txt";
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tmp/child/find_file_in_child_directory.txt", ""));
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
-        let searcher = Searcher::new(params, None, None, tempfile::env::temp_dir().to_str().unwrap().to_string(), None);
-        
-        // Create a directory inside of `env::temp_dir()`
-        let directory = TempDir::new()?;
-        let file_path = directory.path().join(FILE_NAME_WITH_EXTENSION);
-        
-        // Create a file inside of the newly created directory
-        let tmp_file = File::create(file_path.clone())?;
+        let searcher = Searcher::new(params, None, None, "/tmp".to_string(), None).with_file_system(file_system);
         let test_by_name = Test::Name(FILE_NAME_WITH_EXTENSION.to_string());
 
         // Act
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(tempfile::env::temp_dir().as_path(), test_by_name, None, None, Box::clone(&lines));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
+        let (_, logs) = search_to_end(searcher, test_by_name);
 
         // Assert
-        let logs = logger.lock().unwrap();
         let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
-        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs, FILE_NAME_WITH_EXTENSION.to_string()));
+        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs, "/tmp/child/find_file_in_child_directory.txt".to_string()));
 
         // Teardown
-        drop(tmp_file);
-        directory.close()?;
-        Ok(())        
+        Ok(())
     }
 
     #[test]
     fn find_file_in_child_child_directory_2() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        const CHILD_DIR: &'static str = "child_dir";
-        const CHILD_FILE: &'static str = "child_file.txt";
-        const CHILD_FILE_REL_PATH: &'static str = "child_dir/child_file.txt";
-
-
-        let temp_dir = assert_fs::TempDir::new()?;
-        std::env::set_current_dir(temp_dir.path())?;
-
-
+        const CHILD_FILE: &str = "child_file.txt";
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tmp/temp_dir/child_dir/child_file.txt", ""));
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
-
-        let searcher = Searcher::new(params, None, None, temp_dir.path().to_str().unwrap().to_string(), None);
-        let temp_file = temp_dir.child(CHILD_FILE_REL_PATH).touch();
-
+        let searcher = Searcher::new(params, None, None, "/tmp".to_string(), None).with_file_system(file_system);
         let test_by_name = Test::Name(CHILD_FILE.to_string());
+
         // Act
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(tempfile::env::temp_dir().as_path(), test_by_name, None, None, Box::clone(&lines));
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
+        let (_, logs) = search_to_end(searcher, test_by_name);
 
         // Assert
-        let logs = logger.lock().unwrap();
         let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
-
-        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), CHILD_FILE.to_string()),
+        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), "/tmp/temp_dir/child_dir/child_file.txt".to_string()),
             "{}", format!("expected to find {} in logs, but the string could not be found. Full logs: \n{:#?}", CHILD_FILE, stdout_logs));
+
         // Teardown
         Ok(())
     }
 
     #[test]
     fn find_file_in_child_child_directory() -> Result<(), Box<dyn std::error::Error>> {
-        const FILE_NAME: &'static str = "find_file_in_child_child_directory";
-        const FILE_NAME_WITH_EXTENSION: &'static str = "find_file_in_child_child_directory.txt";
-
         // Arrange
-        assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
+        const FILE_NAME_WITH_EXTENSION: &str = "find_file_in_child_child_directory.txt";
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tmp/find_file_in_child_child_directory/child/find_file_in_child_child_directory.txt", ""));
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
-        let searcher = Searcher::new(params, None, None, tempfile::env::temp_dir().to_str().unwrap().to_string(), None);
-
-        let directory = Builder::new().prefix(FILE_NAME).tempdir().unwrap();
-        let temp_dir_child = Builder::new().prefix(FILE_NAME).tempdir_in(directory.path()).unwrap();
-        let file_path = temp_dir_child.path().join(FILE_NAME_WITH_EXTENSION);
-        let tmp_file = File::create(file_path.clone());
+        let searcher = Searcher::new(params, None, None, "/tmp".to_string(), None).with_file_system(file_system);
         let test_by_name = Test::Name(FILE_NAME_WITH_EXTENSION.to_string());
-        
+
         // Act
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(tempfile::env::temp_dir().as_path(), test_by_name, None, None, Box::clone(&lines));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
+        let (_, logs) = search_to_end(searcher, test_by_name);
 
         // Assert
-        let logs = logger.lock().unwrap();
         let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
         assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), FILE_NAME_WITH_EXTENSION.to_string()),
             "{}", format!("expected to find {} in logs, but the string could not be found. Full logs: \n{:#?}", FILE_NAME_WITH_EXTENSION, stdout_logs));
 
         // Teardown
-        drop(tmp_file);
-        temp_dir_child.close()?;
-        directory.close()?;
         Ok(())
     }
 
     #[test]
     fn does_not_find_file_in_child_directory_when_max_depth_is_set_to_zero() -> Result<(), Box<dyn std::error::Error>> {
-        const FILE_NAME_WITH_EXTENSION: &'static str = "find_file_in_child_directory.txt";
-
-        assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
+        // Arrange
+        const FILE_NAME_WITH_EXTENSION: &str = "find_file_in_child_directory.txt";
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tmp/child/find_file_in_child_directory.txt", ""));
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
-        let searcher = Searcher::new(params, Some(0), None, tempfile::env::temp_dir().to_str().unwrap().to_string(), None);
-
-        // Create a directory inside of `env::temp_dir()`
-        let directory = TempDir::new()?;
-        let file_path = directory.path().join(FILE_NAME_WITH_EXTENSION);
-        // Create a file inside of the newly created directory
-        let tmp_file = File::create(file_path.clone())?;
+        let searcher = Searcher::new(params, Some(0), None, "/tmp".to_string(), None).with_file_system(file_system);
         let test_by_name = Test::Name(FILE_NAME_WITH_EXTENSION.to_string());
 
         // Act
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(tempfile::env::temp_dir().as_path(), test_by_name, None, None, Box::clone(&lines));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
+        let (_, logs) = search_to_end(searcher, test_by_name);
 
         // Assert
-        let logs = logger.lock().unwrap();
         let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
         assert!(!TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), FILE_NAME_WITH_EXTENSION.to_string()),
-            "{}", format!("expected to find {} in logs, but the string could not be found. Full logs: \n{:#?}", FILE_NAME_WITH_EXTENSION, stdout_logs));
+            "{}", format!("expected not to find {} in logs, but it was found. Full logs: \n{:#?}", FILE_NAME_WITH_EXTENSION, stdout_logs));
 
         // Teardown
-        drop(tmp_file);
-        directory.close()?;
         Ok(())
     }
 
     #[test]
     fn does_not_find_file_in_child_child_directory_when_max_depth_is_set_to_one() -> Result<(), Box<dyn std::error::Error>> {
-        const FILE_NAME: &'static str = "find_file_in_child_child_directory";
-        const FILE_NAME_WITH_EXTENSION: &'static str = "find_file_in_child_child_directory.txt";
-        assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
+        // Arrange
+        const FILE_NAME_WITH_EXTENSION: &str = "find_file_in_child_child_directory.txt";
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tmp/find_file_in_child_child_directory/child/find_file_in_child_child_directory.txt", ""));
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
-        let searcher = Searcher::new(params, Some(1), None, tempfile::env::temp_dir().to_str().unwrap().to_string(), None);
-
-        let directory = Builder::new().prefix(FILE_NAME).tempdir().unwrap();
-        let temp_dir_child = Builder::new().prefix(FILE_NAME).tempdir_in(directory.path()).unwrap();
-        let file_path = temp_dir_child.path().join(FILE_NAME_WITH_EXTENSION);
-        let tmp_file = File::create(file_path.clone());
+        let searcher = Searcher::new(params, Some(1), None, "/tmp".to_string(), None).with_file_system(file_system);
         let test_by_name = Test::Name(FILE_NAME_WITH_EXTENSION.to_string());
-        
+
         // Act
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(tempfile::env::temp_dir().as_path(), test_by_name, None, None, Box::clone(&lines));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
+        let (_, logs) = search_to_end(searcher, test_by_name);
 
         // Assert
-        let logs = logger.lock().unwrap();
         let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
         assert!(!TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), FILE_NAME_WITH_EXTENSION.to_string()),
-            "{}", format!("expected to find {} in logs, but the string could not be found. Full logs: \n{:#?}", FILE_NAME_WITH_EXTENSION, stdout_logs));
+            "{}", format!("expected not to find {} in logs, but it was found. Full logs: \n{:#?}", FILE_NAME_WITH_EXTENSION, stdout_logs));
 
         // Teardown
-        drop(tmp_file);
-        temp_dir_child.close()?;
-        directory.close()?;
         Ok(())
     }
 
     #[test]
-    fn does_not_find_file_in_current_directory_when_min_depth_is_set_to_one() -> Result<(), Box<dyn std::error::Error>> {
+    fn min_depth_of_one_excludes_only_the_starting_point() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        const FILE_NAME_WITH_EXTENSION: &'static str = "does_not_find_file_in_current_directory_when_min_depth_is_set_to_one.txt";
-        let temp = assert_fs::TempDir::new()?;
-        std::env::set_current_dir(temp.path())?;
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tree/1.txt", "")
+            .with_file("/tree/a/2.txt", ""));
+        let searcher = Searcher::new(Params::default(), None, Some(1), "/tree".to_string(), None).with_file_system(file_system);
+
+        // Act
+        let (_, logs) = search_to_end(searcher, Test::True);
 
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
+        // Assert
+        assert_eq!(stdout_paths(&logs), vec!["/tree/1.txt", "/tree/a", "/tree/a/2.txt"]);
 
-        let params = Params {
-            symlink_setting: SymLinkSetting::Never,
-            debug_opts: None,
-            optimisation_level: None
-        };
+        // Teardown
+        Ok(())
+    }
 
-        let searcher = Searcher::new(params, None, Some(1), temp.path().to_str().unwrap().to_string(), None);
-        temp.child(FILE_NAME_WITH_EXTENSION).touch()?;
-        let test_by_name = Test::Name(FILE_NAME_WITH_EXTENSION.to_string());
+    #[test]
+    fn max_depth_counts_the_starting_point_as_depth_zero() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tree/1.txt", "")
+            .with_file("/tree/a/2.txt", "")
+            .with_file("/tree/a/b/3.txt", ""));
+        let searcher = |max_depth: u32| Searcher::new(Params::default(), Some(max_depth), None, "/tree".to_string(), None).with_file_system(Arc::clone(&file_system));
 
         // Act
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(tempfile::env::temp_dir().as_path(), test_by_name, None, None, Box::clone(&lines));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
-
+        let (_, only_the_starting_point) = search_to_end(searcher(0), Test::True);
+        let (_, two_levels) = search_to_end(searcher(2), Test::True);
 
         // Assert
-        let logs = logger.lock().unwrap();
-        let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
-        assert!(!TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), FILE_NAME_WITH_EXTENSION.to_string()),
-            "{}", format!("expected to find {} in logs, but the string could not be found. Full logs: \n{:#?}", FILE_NAME_WITH_EXTENSION, stdout_logs));
+        assert_eq!(stdout_paths(&only_the_starting_point), vec!["/tree"]);
+        assert_eq!(stdout_paths(&two_levels), vec!["/tree", "/tree/1.txt", "/tree/a", "/tree/a/2.txt", "/tree/a/b"]);
 
+        // Teardown
         Ok(())
     }
 
@@ -548,47 +840,31 @@ mod tests {
     fn does_not_follow_symbolic_links_by_default() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange 
         const FILE_NAME_WITH_EXTENSION: &'static str = "does_not_follow_symbolic_links_by_default.txt";
-        let current_directory = TempDir::new()?;
-        let directory_of_link = TempDir::new()?;
-        let working_directory_before_test = std::env::current_dir().unwrap();
-        assert!(std::env::set_current_dir(current_directory.path()).is_ok());
-
-        let original_file_path = current_directory.path().join(FILE_NAME_WITH_EXTENSION);
-        let original_file = File::create(original_file_path.clone())?;
-
-        let directory_of_link_path = directory_of_link.path().join("symlink");
-        std::os::unix::fs::symlink(&original_file_path, directory_of_link_path.clone())?;
-        
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
+        let file_system = MemoryFileSystem::new()
+            .with_file(format!("/current/{}", FILE_NAME_WITH_EXTENSION), "")
+            .with_symlink("/directory_of_link/symlink", "/current");
 
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
 
-        let searcher = Searcher::new(params, None, None, tempfile::env::temp_dir().to_str().unwrap().to_string(), None); 
+        let searcher = Searcher::new(params, None, None, "/".to_string(), None).with_file_system(Arc::new(file_system));
         let test_by_name = Test::Name(FILE_NAME_WITH_EXTENSION.to_string());
         
         // Act
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(current_directory.path(), test_by_name, None, None, Box::clone(&lines));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
+        let (_, logs) = search_to_end(searcher, test_by_name);
 
 
         // Assert
-        let logs = logger.lock().unwrap();
         let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
-        // return the name of the orginal file and not the symlink.
-        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), FILE_NAME_WITH_EXTENSION.to_string()));
+        // return the orginal file and not the file through the symlink.
+        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), format!("/current/{}", FILE_NAME_WITH_EXTENSION)));
+        assert!(!TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), "symlink".to_string()));
 
         // Teardown
-        current_directory.close()?;
-        directory_of_link.close()?;
-        let _ = std::env::set_current_dir(working_directory_before_test)?;
-        drop(original_file);
         Ok(())
     }
 
@@ -596,132 +872,123 @@ mod tests {
     fn follows_symlink_when_set_to_follow() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
         const FILE_NAME_WITH_EXTENSION: &'static str = "follows_symlink_when_set_to_follow.txt";
-        let directory_of_file = TempDir::new()?;
-        let directory_of_link = TempDir::new()?;
-        // let working_directory_before_test = std::env::current_dir().unwrap();
-        assert!(std::env::set_current_dir(directory_of_link.path()).is_ok());
-
-        let original_file_path = directory_of_file.path().join(FILE_NAME_WITH_EXTENSION);
-        let original_file = File::create(original_file_path.clone())?;
-
-        let directory_of_link_path = directory_of_link.path().join("symlink");
-        std::os::unix::fs::symlink(&original_file_path, directory_of_link_path.clone())?;
-        
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
+        let file_system = MemoryFileSystem::new()
+            .with_file(format!("/directory_of_file/{}", FILE_NAME_WITH_EXTENSION), "")
+            .with_symlink("/directory_of_link/symlink", "../directory_of_file");
 
         let params = Params {
             symlink_setting: SymLinkSetting::Follow,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
 
-        let searcher = Searcher::new(params, None, None, tempfile::env::temp_dir().to_str().unwrap().to_string(), None);
+        let searcher = Searcher::new(params, None, None, "/directory_of_link".to_string(), None).with_file_system(Arc::new(file_system));
         let test_by_name = Test::Name(FILE_NAME_WITH_EXTENSION.to_string());
         
         // Act
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(directory_of_link.path(), test_by_name, None, None, Box::clone(&lines));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
+        let (_, logs) = search_to_end(searcher, test_by_name);
 
         // Assert
-        let logs = logger.lock().unwrap();
         let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
-        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), FILE_NAME_WITH_EXTENSION.to_string()),
-            "{}", format!("expected to find {} in logs, but the string could not be found. Full logs: \n{:#?}", FILE_NAME_WITH_EXTENSION, stdout_logs));
+        let path_through_link = format!("/directory_of_link/symlink/{}", FILE_NAME_WITH_EXTENSION);
+        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), path_through_link.clone()),
+            "{}", format!("expected to find {} in logs, but the string could not be found. Full logs: \n{:#?}", path_through_link, stdout_logs));
 
         // Teardown
-        directory_of_file.close()?;
-        directory_of_link.close()?;
-        // let _ = std::env::set_current_dir(working_directory_before_test)?;
-        drop(original_file);
         Ok(())
     }
 
     #[test]
     fn handle_broken_symlink() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        const FILE_NAME_WITH_EXTENSION: &'static str = "handle_broken_symlink.txt";
-        let directory_of_file = TempDir::new()?;
-        let directory_of_link = TempDir::new()?;
-        // let working_directory_before_test = std::env::current_dir().unwrap();
-        assert!(std::env::set_current_dir(directory_of_link.path()).is_ok());
-
-        let original_file_path = directory_of_file.path().join(FILE_NAME_WITH_EXTENSION);
-        let original_file = File::create(original_file_path.clone())?;
-
-        let directory_of_link_path = directory_of_link.path().join("symlink");
-        std::os::unix::fs::symlink(&original_file_path, directory_of_link_path.clone())?;
-
-        //delete the original file to create a broken symlink
-        std::fs::remove_file(original_file_path.clone())?;
-        
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
+        // the file that the link points to is never created
+        let file_system = MemoryFileSystem::new()
+            .with_directory("/directory_of_file")
+            .with_symlink("/directory_of_link/symlink", "/directory_of_file/handle_broken_symlink.txt");
 
         let params = Params {
             symlink_setting: SymLinkSetting::Follow,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
 
-        let searcher = Searcher::new(params, None, None, tempfile::env::temp_dir().to_str().unwrap().to_string(), None);
-        let test_by_name = Test::Name(FILE_NAME_WITH_EXTENSION.to_string());
+        let searcher = Searcher::new(params, None, None, "/directory_of_link".to_string(), None).with_file_system(Arc::new(file_system));
+        let test_by_name = Test::Name("symlink".to_string());
         
         // Act
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(directory_of_link.path(), test_by_name, None, None, Box::clone(&lines));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
+        let (_, logs) = search_to_end(searcher, test_by_name);
 
         // Assert
-        let logs = logger.lock().unwrap();
         let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
-        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), FILE_NAME_WITH_EXTENSION.to_string()),
-            "{}", format!("expected to find {} in logs, but the string could not be found. Full logs: \n{:#?}", FILE_NAME_WITH_EXTENSION, stdout_logs));
+        // a broken link can not be followed, so it is found as the link itself
+        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), "/directory_of_link/symlink".to_string()),
+            "{}", format!("expected to find /directory_of_link/symlink in logs, but the string could not be found. Full logs: \n{:#?}", stdout_logs));
 
         // Teardown
-        directory_of_file.close()?;
-        directory_of_link.close()?;
-        // let _ = std::env::set_current_dir(working_directory_before_test)?;
-        drop(original_file);
         Ok(())
 
     }
 
+    #[test]
+    fn searches_a_file_system_in_memory_like_one_on_disk() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tree/1.txt", "12345")
+            .with_file("/tree/a/2.txt", "")
+            .with_symlink("/tree/a/up", "..")
+            .with_symlink("/tree/a/self", "self"));
+        let paths = |entries: Vec<Entry>| entries.into_iter().map(|entry| entry.path).collect::<Vec<_>>();
+
+        // Act
+        let found: Vec<Entry> = SearchBuilder::new("/tree").threads(1).file_system(Arc::clone(&file_system)).build()?.collect::<Result<_, _>>()?;
+        let (followed, errors): (Vec<_>, Vec<_>) = SearchBuilder::new("/tree").threads(1).file_system(Arc::clone(&file_system))
+            .follow_links(SymLinkSetting::Follow).filter(Test::Types("f".to_string())).build()?.partition(Result::is_ok);
+
+        // Assert
+        let expected: Vec<PathBuf> = ["/tree", "/tree/1.txt", "/tree/a", "/tree/a/2.txt", "/tree/a/self", "/tree/a/up"].iter().map(PathBuf::from).collect();
+        assert_eq!(paths(found.clone()), expected);
+        assert_eq!(found[1].stat().size, 5);
+        assert_eq!(found[5].link_target(), Some(Path::new("..")));
+        assert_eq!(paths(followed.into_iter().collect::<Result<_, _>>()?), vec![PathBuf::from("/tree/1.txt"), PathBuf::from("/tree/a/2.txt")]);
+        // `up` leads back to where the search started, and `self` can not be followed at all
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].as_ref().unwrap_err().to_string().contains("File system loop detected"));
+        assert!(file_system.metadata(Path::new("/tree/a/self"), true).is_err());
+        assert!(Directory::open_in(Arc::clone(&file_system), Path::new("/tree/1.txt"), true).is_err());
+        let mut contents = String::new();
+        file_system.open(Path::new("/tree/1.txt"))?.read_to_string(&mut contents)?;
+        assert_eq!(contents, "12345");
+
+        // Teardown
+        Ok(())
+    }
+
     #[test]
     fn check_debug_opts_tree_logs_correctly() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        let temp = assert_fs::TempDir::new().unwrap();
-        let inputs_files = vec![temp.child("foo1.txt"), temp.child("foo2.txt"), temp.child("check_debug_opts_tree_logs_correctly_sub_dir/foo3.txt"), temp.child("check_debug_opts_tree_logs_correctly_sub_dir/foo4.txt")];
-        for input_file in inputs_files {
-            input_file.touch()?;
-        }
+        let inputs_files = ["/tree/foo1.txt", "/tree/foo2.txt", "/tree/check_debug_opts_tree_logs_correctly_sub_dir/foo3.txt", "/tree/check_debug_opts_tree_logs_correctly_sub_dir/foo4.txt"];
+        let file_system: Arc<dyn FileSystem> = Arc::new(inputs_files.iter().fold(MemoryFileSystem::new(), |file_system, input_file| file_system.with_file(input_file, "")));
 
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
 
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: Some(DebugOpts::Exec),
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
 
 
-        let searcher = Searcher::new(params, None, None, tempfile::env::temp_dir().to_str().unwrap().to_string(), None);
+        let searcher = Searcher::new(params, None, None, "/tree".to_string(), None).with_file_system(file_system);
         let test = Test::Name("foo4.txt".to_string());
 
         // Act
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(temp.path(), test, None, None, Box::clone(&lines));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
+        let (_, logs) = search_to_end(searcher, test);
 
 
         // Assert
         // let stdout_logs = logger.lock().unwrap().get_logs_by_type(discriminant(&LogLine::StdOut(String::new())));
-        let logs = logger.lock().unwrap();
         let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
         assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs, "foo4.txt".to_string()));
         
@@ -736,120 +1003,887 @@ mod tests {
     #[test]
     fn checks_only_files_and_directories_that_are_empty() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        let temp = assert_fs::TempDir::new()?;
-        std::env::set_current_dir(temp.path())?;
-
-        let empty_dir = temp.child("empty_dir/").touch();
-        let empty_file = temp.child("empty_file.txt").touch();
-
-        let populated_dir = temp.child("populated_dir/");
-        let populated_file = temp.child("populated_dir/populated_file.txt").write_str("some data");
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_directory("/tree/empty_dir")
+            .with_file("/tree/empty_file.txt", "")
+            .with_file("/tree/populated_dir/populated_file.txt", "some data"));
 
-        let logger = Rc::new(Mutex::new(TestLogger::new()));
 
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
 
         // todo add more
         let test = Test::Name("empty_file.txt".to_string());
         
-        let searcher = Searcher::new(params, None, None, std::env::current_dir().unwrap().to_str().unwrap().to_string(), None);
-        let lines = Box::new(Vec::new());
-        Arc::new(searcher).search_directory_path(temp.path(), test, None, None, Box::clone(&lines));
-        for line in *lines {
-            logger.lock().unwrap().log(line); 
-        }
+        let searcher = Searcher::new(params, None, None, "/tree".to_string(), None).with_file_system(file_system);
+        search_to_end(searcher, test);
         
         Ok(())
     }
 
-    #[test_case("--false", "--false", false ; "Expect false when both operands are false")]
-    #[test_case("--false", "--true", false ; "Expect false when first operand is false and second operand is true")]
-    #[test_case("--true", "--false", false ; "Expect false when first operand is true and second operand is false")]
-    #[test_case("--true", "--true", true ; "Expect true when both operands are true")]
-    fn check_and_operator_works(first_operand: &str, second_operand: &str, expected: bool) -> Result<(), Box<dyn std::error::Error>> {
-        let temp = assert_fs::TempDir::new()?;
-        std::env::set_current_dir(temp.path())?;
+    #[test]
+    fn search_builder_leaves_the_entries_it_yields_to_be_stat_ed_when_asked() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        // counts the files that are stat'ed
+        let search = |files: usize| -> Result<(Vec<Entry>, Arc<AtomicUsize>), Box<dyn std::error::Error>> {
+            let stat_ed = Arc::new(AtomicUsize::new(0));
+            let counter = Arc::clone(&stat_ed);
+            let file_system = (0..files).fold(MemoryFileSystem::new(), |file_system, i| file_system.with_file(format!("/tree/{}.txt", i), "12345"));
+            let file_system = Arc::new(Hooked(file_system, move |_: &Path| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }));
+            let found = SearchBuilder::new("/tree").threads(1).file_system(file_system).build()?.collect::<Result<_, _>>()?;
+            Ok((found, stat_ed))
+        };
 
-        let params = Params {
-            symlink_setting: SymLinkSetting::Never,
-            debug_opts: None,
-            optimisation_level: None
+        // Act
+        let (found_in_one, stat_ed_in_one) = search(1)?;
+        let (found_in_fifty, stat_ed_in_fifty) = search(50)?;
+        let stat_ed = stat_ed_in_fifty.load(Ordering::SeqCst);
+        let size = found_in_fifty[1].stat().size;
+
+        // Assert
+        assert_eq!((found_in_one.len(), found_in_fifty.len()), (2, 51));
+        // only the starting point is stat'ed, however many files it holds
+        assert_eq!(stat_ed, stat_ed_in_one.load(Ordering::SeqCst));
+        assert_eq!(size, 5);
+        assert_eq!(stat_ed_in_fifty.load(Ordering::SeqCst), stat_ed + 1);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn files_removed_during_the_search_are_warned_about_and_only_fail_it_without_ignore_readdir_race() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        // the files whose names start with vanished can no longer be found when they are stat'ed
+        let file_system: Arc<dyn FileSystem> = Arc::new(Hooked(MemoryFileSystem::new()
+            .with_file("/tree/kept.txt", "")
+            .with_file("/tree/vanished.txt", ""), |path: &Path| match path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("vanished")) {
+                true => Err(io::Error::from_raw_os_error(libc::ENOENT)),
+                false => Ok(())
+            }));
+        // every file is stat'ed for --ls, which is when the one that vanished is missed
+        let searcher = |ignore_readdir_race: bool| {
+            let params = Params {
+                actions: vec![Action::Ls(FileDescriptor::StdOut)],
+                ignore_readdir_race,
+                ..Default::default()
+            };
+            Searcher::new(params, None, None, "/tree".to_string(), None).with_file_system(Arc::clone(&file_system))
         };
-        let searcher = Searcher::new(params, None, None, temp.path().to_str().unwrap().to_string(), None);
 
-        let operator = format!("--and");
-        let tokens = [ first_operand.to_owned(), operator, second_operand.to_owned() ].to_vec();
+        // Act
+        let (ignoring, logs) = search_to_end(searcher(true), Test::True);
+        let (not_ignoring, _) = search_to_end(searcher(false), Test::True);
+
+        // Assert
+        let warnings = logs.get_logs_by_file_descriptor(FileDescriptor::StdErr);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.get_contained_message().contains("/tree/vanished.txt vanished during the search"), "{:#?}", warnings);
+        assert_eq!(ignoring.errors().count(ErrorCategory::Vanished), 1);
+        assert!(!ignoring.has_failed());
+        assert_eq!(not_ignoring.errors().count(ErrorCategory::Vanished), 1);
+        assert!(not_ignoring.has_failed());
 
-        assert_eq!(eval(tokens, Arc::new(searcher)), expected);
+        // Teardown
         Ok(())
     }
 
-    #[test_case("--false", "--false", false ; "Expect false when both operands are false")]
-    #[test_case("--false", "--true", true ; "Expect true when first operand is false and second operand is true")]
-    #[test_case("--true", "--false", true ; "Expect true when first operand is true and second operand is false")]
-    #[test_case("--true", "--true", true ; "Expect true when both operands are true")]
-    fn check_or_operator_works(first_operand: &str, second_operand: &str, expected: bool) -> Result<(), Box<dyn std::error::Error>> {
+    #[test]
+    fn delete_removes_matched_files_and_empty_directories() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
         let temp = assert_fs::TempDir::new()?;
-        std::env::set_current_dir(temp.path())?;
+        temp.child("delete_me/delete_me").touch()?;
+        temp.child("keep_dir/delete_me").touch()?;
+        temp.child("keep_dir/keep_me.txt").touch()?;
+
 
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            actions: vec![Action::Delete],
+            depth_first: true,
+            ..Default::default()
         };
+
         let searcher = Searcher::new(params, None, None, temp.path().to_str().unwrap().to_string(), None);
+        let test = Test::Name("delete_me".to_string());
 
-        let operator = format!("--or");
-        let tokens = [ first_operand.to_owned(), operator, second_operand.to_owned() ].to_vec();
+        // Act
+        let (searcher, logs) = search_to_end(searcher, test);
 
-        assert_eq!(eval(tokens, Arc::new(searcher)), expected);
-        Ok(())
-    }
+        // Assert
+        assert!(logs.get_logs_by_file_descriptor(FileDescriptor::StdErr).is_empty(), "{:#?}", logs.get_logs());
+        assert!(!searcher.has_failed());
+        temp.child("delete_me").assert(predicates::path::missing());
+        temp.child("keep_dir/delete_me").assert(predicates::path::missing());
+        temp.child("keep_dir/keep_me.txt").assert(predicates::path::exists());
 
-    #[test_case("--true", false ; "Expect false when operand is true")]
-    #[test_case("--false", true ; "Expect true when operand is false")]
-    fn check_not_operator_works(operand: &str, expected: bool) -> Result<(), Box<dyn std::error::Error>> {
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn delete_reports_directories_that_are_not_empty() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
         let temp = assert_fs::TempDir::new()?;
-        std::env::set_current_dir(temp.path())?;
+        temp.child("delete_me/keep_me.txt").touch()?;
+
 
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            actions: vec![Action::Delete],
+            depth_first: true,
+            ..Default::default()
         };
+
         let searcher = Searcher::new(params, None, None, temp.path().to_str().unwrap().to_string(), None);
+        let test = Test::Name("delete_me".to_string());
 
-        let operator = format!("--not");
-        let tokens = [operator, operand.to_owned()].to_vec();
+        // Act
+        let (searcher, logs) = search_to_end(searcher, test);
+
+        // Assert
+        let stderr_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdErr);
+        assert!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stderr_logs.clone(), "cannot delete".to_string()),
+            "{}", format!("expected an error for the non-empty directory. Full logs: \n{:#?}", stderr_logs));
+        assert!(searcher.has_failed());
+        temp.child("delete_me/keep_me.txt").assert(predicates::path::exists());
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn sink_logs_more_lines_than_its_capacity() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let file_system: Arc<dyn FileSystem> = Arc::new((0..50).fold(MemoryFileSystem::new(), |file_system, i| {
+            file_system.with_file(format!("/tree/dir_{}/sink_logs_more_lines_than_its_capacity.txt", i), "")
+        }));
+
+        let params = Params {
+            symlink_setting: SymLinkSetting::Never,
+            debug_opts: None,
+            optimisation_level: None,
+            ..Default::default()
+        };
+
+        let threadpool = Some(Arc::new(ThreadPool::new(4)));
+        let searcher = Searcher::new(params, None, None, "/tree".to_string(), threadpool).with_file_system(file_system);
+        let test = Test::Name("sink_logs_more_lines_than_its_capacity.txt".to_string());
+
+        // Act
+        let (sink, output_thread) = Sink::new(1, TestLogger::new());
+        Arc::new(searcher).search_directory_path(Path::new("/tree"), test, None, None, sink);
+
+        // Assert
+        let logs = output_thread.join().unwrap();
+        assert_eq!(logs.get_logs_by_file_descriptor(FileDescriptor::StdOut).len(), 50);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn threadpool_waits_for_jobs_queued_by_other_jobs() {
+        // Arrange
+        fn spawn(threadpool: Arc<ThreadPool>, counter: Arc<AtomicUsize>, depth: u32) {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if depth == 0 {
+                return;
+            }
+            for _ in 0..4 {
+                let (threadpool_2, counter) = (Arc::clone(&threadpool), Arc::clone(&counter));
+                threadpool.execute(move || spawn(threadpool_2, counter, depth - 1));
+            }
+        }
+        let threadpool = Arc::new(ThreadPool::new(4));
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        // Act
+        spawn(Arc::clone(&threadpool), Arc::clone(&counter), 5);
+        threadpool.wait();
+
+        // Assert
+        // 1 + 4 + 16 + 64 + 256 + 1024 calls
+        assert_eq!(counter.load(Ordering::SeqCst), 1365);
+        threadpool.wait();
+    }
+
+    #[test]
+    fn threadpool_counts_jobs_that_panic_and_keeps_running_the_rest() {
+        // Arrange
+        let threadpool = ThreadPool::new(2);
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        // Act
+        for i in 0..10 {
+            let counter = Arc::clone(&counter);
+            threadpool.execute(move || {
+                if i % 5 == 0 {
+                    panic!("job {} panicked on purpose", i);
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        threadpool.wait();
+
+        // Assert
+        assert_eq!(threadpool.panicked(), 2);
+        assert_eq!(counter.load(Ordering::SeqCst), 8);
+    }
+
+    #[test]
+    fn thread_count_prefers_the_option_then_the_environment() {
+        let available = std::thread::available_parallelism().unwrap().get();
+        assert_eq!(thread_count(Some(3), Some("5".to_string())), 3);
+        assert_eq!(thread_count(None, Some("5".to_string())), 5);
+        assert_eq!(thread_count(None, Some("0".to_string())), available);
+        assert_eq!(thread_count(None, Some("many".to_string())), available);
+        assert_eq!(thread_count(None, None), available);
+    }
+
+    #[test]
+    fn search_builder_yields_the_entries_it_finds_and_the_errors_it_runs_into() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tree/1.txt", "")
+            .with_file("/tree/a/2.txt", "")
+            .with_file("/tree/a/b/3.txt", ""));
+        let search = || SearchBuilder::new("/tree").file_system(Arc::clone(&file_system));
+        let paths = |entries: Vec<Entry>| {
+            let mut paths: Vec<PathBuf> = entries.into_iter().map(|entry| entry.path.strip_prefix("/tree").unwrap().to_path_buf()).collect();
+            paths.sort();
+            paths
+        };
+
+        // Act
+        let everything: Vec<Entry> = search().threads(4).build()?.collect::<Result<_, _>>()?;
+        let files: Vec<Entry> = search().max_depth(2).filter(Test::Types("f".to_string())).build()?.collect::<Result<_, _>>()?;
+        let (found, errors): (Vec<_>, Vec<_>) = search().path("/tree/missing").max_depth(0).build()?.partition(Result::is_ok);
+        // the search stops once the iterator is dropped
+        let first = search().threads(1).build()?.next();
+        let invalid_regex = search().filter(Test::Regex("*.txt".to_string())).build();
+
+        // Assert
+        let expected: Vec<PathBuf> = ["", "1.txt", "a", "a/2.txt", "a/b", "a/b/3.txt"].iter().map(PathBuf::from).collect();
+        assert_eq!(paths(everything), expected);
+        assert_eq!(paths(files), vec![PathBuf::from("1.txt"), PathBuf::from("a/2.txt")]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].as_ref().unwrap_err().to_string().contains("missing"));
+        assert_eq!(first.unwrap()?.path, Path::new("/tree"));
+        assert!(invalid_regex.is_err_and(|error| error.starts_with("invalid regular expression *.txt")));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct LargerThan(u64, AtomicUsize);
+
+    impl Predicate for LargerThan {
+        fn matches(&self, entry: &Entry) -> bool {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            entry.stat().size > self.0
+        }
+
+        fn cost(&self) -> Cost {
+            Cost::Expensive
+        }
+
+        fn stat_mask(&self) -> u32 {
+            STAT_ALL
+        }
+    }
+
+    #[test]
+    fn custom_predicates_are_searched_with_and_checked_after_cheaper_ones() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tree/small/custom.txt", "small")
+            .with_file("/tree/large/custom.txt", "larger than ten bytes")
+            .with_file("/tree/large/other.txt", "larger than ten bytes"));
+        let larger_than = Arc::new(LargerThan(10, AtomicUsize::new(0)));
+        let predicate = All::new(vec![Arc::clone(&larger_than) as Arc<dyn Predicate>, Arc::new(Name("custom.txt".to_string()))]);
+
+        // Act
+        let found: Vec<Entry> = SearchBuilder::new("/tree").file_system(file_system).filter(Test::Custom(Arc::new(predicate))).build()?.collect::<Result<_, _>>()?;
+
+        // Assert
+        let found: Vec<&Path> = found.iter().map(|entry| entry.path.as_path()).collect();
+        assert_eq!(found, vec![Path::new("/tree/large/custom.txt")]);
+        // the size was only checked for the entries with the right name, as the name is cheaper to check
+        assert_eq!(larger_than.1.load(Ordering::SeqCst), 2);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test_case(None, 2 ; "Expect the cheaper name to be checked first")]
+    #[test_case(Some(0), 6 ; "Expect the order of the expression to be kept at optimisation level 0")]
+    fn registered_predicates_are_named_by_the_expression_and_and_checks_the_cheaper_ones_first(optimisation_level: Option<u8>, expected: usize) -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tree/small/custom.txt", "small")
+            .with_file("/tree/large/custom.txt", "larger than ten bytes")
+            .with_file("/tree/large/other.txt", "larger than ten bytes"));
+        let larger_than = Arc::new(LargerThan(10, AtomicUsize::new(0)));
+        let registry = Registry::new().with("--larger", Arc::clone(&larger_than) as Arc<dyn Predicate>);
+        let tokens: Vec<String> = ["--larger", "--and", "--name", "custom.txt"].map(String::from).to_vec();
+        let searcher = Searcher::new(Params::default(), None, None, "/tree".to_string(), None).with_file_system(file_system);
+
+        // Act
+        let predicate = registry.parse(&tokens, optimisation_level)?;
+        let (_, logs) = search_to_end(searcher, Test::Custom(predicate));
+
+        // Assert
+        assert_eq!(stdout_paths(&logs), vec!["/tree/large/custom.txt"]);
+        assert_eq!(larger_than.1.load(Ordering::SeqCst), expected);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn search_builder_expression_names_the_predicates_given_to_it() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tree/small/custom.txt", "small")
+            .with_file("/tree/large/custom.txt", "larger than ten bytes")
+            .with_file("/tree/large/other.txt", "larger than ten bytes"));
+        let builder = SearchBuilder::new("/tree").file_system(file_system)
+            .predicate("--larger", Arc::new(LargerThan(10, AtomicUsize::new(0))));
+
+        // Act
+        let found: Vec<Entry> = builder.clone().expression(["--name", "custom.txt", "--larger"]).build()?.collect::<Result<_, _>>()?;
+        let unknown = builder.expression(["--smaller"]).build();
+
+        // Assert
+        let found: Vec<&Path> = found.iter().map(|entry| entry.path.as_path()).collect();
+        assert_eq!(found, vec![Path::new("/tree/large/custom.txt")]);
+        assert!(unknown.is_err_and(|error| error == "unknown test --smaller"));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn entry_stream_yields_every_entry_and_stops_the_search_when_dropped() -> Result<(), Box<dyn std::error::Error>> {
+        use std::pin::Pin;
+        use std::task::{Context, Poll, Wake, Waker};
+        use futures_core::Stream;
+
+        // polls on this thread, which sleeps until the stream wakes it
+        struct Unpark(std::thread::Thread);
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        // Arrange
+        // more than the stream holds, so that the search has to wait for it
+        let file_system: Arc<dyn FileSystem> = Arc::new((0..1500).fold(MemoryFileSystem::new(), |file_system, i| {
+            file_system.with_file(format!("/tree/{}/stream_{}.txt", i % 10, i), "")
+        }));
+        let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+
+        // Act
+        let mut stream = SearchBuilder::new("/tree").file_system(Arc::clone(&file_system)).filter(Test::Types("f".to_string())).build_stream()?;
+        let mut found = Vec::new();
+        loop {
+            match Pin::new(&mut stream).poll_next(&mut cx) {
+                Poll::Ready(Some(entry)) => found.push(entry),
+                Poll::Ready(None) => break,
+                Poll::Pending => std::thread::park()
+            }
+        }
+        let mut first = SearchBuilder::new("/tree").file_system(file_system).threads(1).build_stream()?;
+        let starting_point = loop {
+            match Pin::new(&mut first).poll_next(&mut cx) {
+                Poll::Ready(entry) => break entry,
+                Poll::Pending => std::thread::park()
+            }
+        };
+        drop(first);
+
+        // Assert
+        assert_eq!(found.len(), 1500);
+        assert!(found.iter().all(|entry| entry.name().to_string_lossy().starts_with("stream_")));
+        assert_eq!(stream.errors().total(), 0);
+        assert_eq!(starting_point.unwrap().path, Path::new("/tree"));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn parse_duration_accepts_a_number_and_a_unit() {
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5m"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert!(parse_duration("3x").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("99999999999999999999h").is_err());
+    }
+
+    #[test]
+    fn search_that_has_timed_out_stops_before_the_first_entry() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new().with_file("/tree/timed_out/timed_out.txt", ""));
+        let searcher = Searcher::new(Params::default(), None, None, "/tree".to_string(), None)
+            .with_file_system(file_system)
+            .with_timeout(Duration::ZERO);
+
+        // Act
+        let (searcher, logs) = search_to_end(searcher, Test::Name("timed_out.txt".to_string()));
+
+        // Assert
+        assert!(logs.get_logs().is_empty(), "{:#?}", logs.get_logs());
+        assert_eq!(searcher.stop().reason(), Some(StopReason::TimedOut));
+        assert!(!searcher.has_failed());
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn deleting_from_a_file_system_that_can_not_be_changed_fails_for_every_entry() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tree/a.txt", "")
+            .with_file("/tree/b/c.txt", ""));
+        let params = Params { actions: vec![Action::Delete], depth_first: true, ..Default::default() };
+        let searcher = Searcher::new(params, None, None, "/tree".to_string(), None).with_file_system(file_system);
+
+        // Act
+        let (searcher, logs) = search_to_end(searcher, Test::True);
+
+        // Assert
+        let stderr = logs.get_logs_by_file_descriptor(FileDescriptor::StdErr);
+        // both the entries read from a directory and the starting point are removed through the file system
+        assert_eq!(stderr.len(), 4, "{:#?}", stderr);
+        assert!(stderr.iter().all(|line| line.message.get_contained_message().contains("can not be removed from this file system")));
+        assert!(searcher.has_failed());
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn searches_and_deletes_a_tree_ten_thousand_levels_deep_with_few_directories_open() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        const DEPTH: usize = 10_000;
+        let temp = assert_fs::TempDir::new()?;
+        // the path is far longer than PATH_MAX, so the tree is made relative to each directory in turn
+        let root = std::ffi::CString::new(temp.path().as_os_str().as_bytes())?;
+        let mut directory = unsafe { libc::open(root.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY) };
+        let name = std::ffi::CString::new("d")?;
+        for _ in 0..DEPTH {
+            let child = unsafe {
+                libc::mkdirat(directory, name.as_ptr(), 0o755);
+                libc::openat(directory, name.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY)
+            };
+            unsafe { libc::close(directory) };
+            assert!(child >= 0);
+            directory = child;
+        }
+        let file = std::ffi::CString::new("deep.txt")?;
+        unsafe {
+            libc::close(libc::openat(directory, file.as_ptr(), libc::O_CREAT | libc::O_WRONLY, 0o644));
+            libc::close(directory);
+        }
+        // the starting point is kept, to check that everything below it was deleted
+        let below = |params: Params| Searcher::new(params, None, Some(1), temp.path().to_str().unwrap().to_string(), None).with_directory_limit(32);
+
+        // Act
+        let (searcher, logs) = search_to_end(below(Params::default()), Test::Name("deep.txt".to_string()));
+        let delete = Params { actions: vec![Action::Delete], depth_first: true, ..Default::default() };
+        let (deleter, delete_logs) = search_to_end(below(delete), Test::Types("df".to_string()));
+
+        // Assert
+        let stdout = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
+        assert_eq!(stdout.len(), 1, "{:#?}", logs.get_logs_by_file_descriptor(FileDescriptor::StdErr));
+        let found = stdout[0].message.get_contained_message();
+        assert_eq!(Path::new(found.as_ref()).strip_prefix(temp.path())?.components().count(), DEPTH + 1);
+        assert!(!searcher.has_failed());
+        assert!(delete_logs.get_logs().is_empty(), "{:#?}", delete_logs.get_logs());
+        assert!(!deleter.has_failed());
+        assert_eq!(std::fs::read_dir(temp.path())?.count(), 0);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn queued_directories_of_a_deep_tree_are_opened_relative_to_the_directories_above_them() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        const DEPTH: usize = 1_000;
+        let temp = assert_fs::TempDir::new()?;
+        // the path is far longer than PATH_MAX, so the tree is made relative to each directory in turn
+        let root = std::ffi::CString::new(temp.path().as_os_str().as_bytes())?;
+        let mut directory = unsafe { libc::open(root.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY) };
+        let name = std::ffi::CString::new("level")?;
+        let file = std::ffi::CString::new("level.txt")?;
+        for _ in 0..DEPTH {
+            let child = unsafe {
+                libc::close(libc::openat(directory, file.as_ptr(), libc::O_CREAT | libc::O_WRONLY, 0o644));
+                libc::mkdirat(directory, name.as_ptr(), 0o755);
+                libc::openat(directory, name.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY)
+            };
+            unsafe { libc::close(directory) };
+            assert!(child >= 0);
+            directory = child;
+        }
+        unsafe { libc::close(directory) };
+        let searcher = |breadth_first: bool, directory_limit: usize| {
+            let params = Params { breadth_first, ..Default::default() };
+            let threadpool = Some(Arc::new(ThreadPool::new(8)));
+            Searcher::new(params, None, None, temp.path().to_str().unwrap().to_string(), threadpool).with_directory_limit(directory_limit)
+        };
+        let files = || Test::Types("f".to_string());
+
+        // Act
+        let breadth_first = search_to_end(searcher(true, 1024), files());
+        // with no directories allowed open, every directory that is queued is opened again once it is searched
+        let depth_first_with_none_open = search_to_end(searcher(false, 0), files());
+        let breadth_first_with_none_open = search_to_end(searcher(true, 0), files());
+
+        // Assert
+        for (searcher, logs) in [breadth_first, depth_first_with_none_open, breadth_first_with_none_open] {
+            assert!(logs.get_logs_by_file_descriptor(FileDescriptor::StdErr).is_empty(), "{:#?}", logs.get_logs_by_file_descriptor(FileDescriptor::StdErr));
+            assert_eq!(logs.get_logs_by_file_descriptor(FileDescriptor::StdOut).len(), DEPTH);
+            assert!(!searcher.has_failed());
+        }
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn stable_order_matches_the_order_of_a_sequential_search() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let mut file_system = MemoryFileSystem::new();
+        for i in 0..20 {
+            for j in 0..5 {
+                file_system = file_system.with_file(format!("/tree/dir_{}/dir_{}/stable_order.txt", i, j), "");
+            }
+            file_system = file_system.with_file(format!("/tree/dir_{}/stable_order.txt", i), "");
+        }
+        let file_system: Arc<dyn FileSystem> = Arc::new(file_system);
+        let search = |threadpool: Option<Arc<ThreadPool>>, order: Order| {
+            let searcher = Searcher::new(Params::default(), None, None, "/tree".to_string(), threadpool).with_file_system(Arc::clone(&file_system));
+            let (sink, output_thread) = Sink::with_order(OUTPUT_CAPACITY, TestLogger::new(), order);
+            Arc::new(searcher).search_directory_path(Path::new("/tree"), Test::Name("stable_order.txt".to_string()), None, None, sink);
+            let logs = output_thread.join().unwrap();
+            logs.get_logs().into_iter().map(|line| line.message.get_contained_message().into_owned()).collect::<Vec<String>>()
+        };
+
+        // Act
+        let sequential = search(None, Order::Traversal);
+        let stable = search(Some(Arc::new(ThreadPool::new(4))), Order::Stable);
+
+        // Assert
+        assert_eq!(sequential.len(), 120);
+        assert_eq!(stable, sequential);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn directory_entries_are_read_with_their_types() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("file.txt").touch()?;
+        temp.child("directory").create_dir_all()?;
+        temp.child("link").symlink_to_file(temp.child("file.txt").path())?;
+        for i in 0..1000 {
+            temp.child(format!("directory/{:0>200}", i)).touch()?;
+        }
+
+        // Act
+        let directory = Directory::open(temp.path())?;
+        let mut entries = directory.entries()?;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        let child_entries = directory.open_at(OsStr::new("directory"), false)?.entries()?;
+        let mut os_entries = Os.read_dir(temp.path())?;
+        os_entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // Assert
+        let names: Vec<&OsStr> = entries.iter().map(|entry| entry.name.as_os_str()).collect();
+        assert_eq!(names, vec![OsStr::new("directory"), OsStr::new("file.txt"), OsStr::new("link")]);
+        // file systems that do not give the type leave it to a stat call
+        for (entry, expected) in entries.iter().zip([FileType::Directory, FileType::File, FileType::Symlink]) {
+            let stat = directory.stat_at(&entry.name, false)?;
+            assert_eq!(FileType::from_mode(stat.mode), expected);
+            assert!(entry.file_type.is_none() || entry.file_type == Some(expected));
+        }
+        // the file system of the OS read by path gives the type of a link itself, as getdents does
+        let os_types: Vec<Option<FileType>> = os_entries.iter().map(|entry| entry.file_type).collect();
+        assert_eq!(os_types, vec![Some(FileType::Directory), Some(FileType::File), Some(FileType::Symlink)]);
+        // more than fit in one read
+        assert_eq!(child_entries.len(), 1000);
+        assert!(directory.open_at(OsStr::new("link"), false).is_err());
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn only_the_fields_that_are_written_or_sorted_by_are_fetched() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let name_only = Action::Printf(FileDescriptor::StdOut, Printf::parse("%p %f\\n")?);
+        let size_and_mtime = Action::Printf(FileDescriptor::StdOut, Printf::parse("%p %s %TY\\n")?);
+
+        // Act
+        let print_mask = Action::Print(FileDescriptor::StdOut).stat_mask(&OutputFormat::Standard);
+        let csv_print_mask = Action::Print(FileDescriptor::StdOut).stat_mask(&OutputFormat::Csv);
+        let json_print_mask = Action::Print(FileDescriptor::StdOut).stat_mask(&OutputFormat::Json);
+        let ls_mask = Action::Ls(FileDescriptor::StdOut).stat_mask(&OutputFormat::Standard);
+
+        // Assert
+        assert_eq!(print_mask, 0);
+        // the columns are added to the mask of the searcher
+        assert_eq!(csv_print_mask, 0);
+        assert_eq!(name_only.stat_mask(&OutputFormat::Standard), 0);
+        assert_eq!(size_and_mtime.stat_mask(&OutputFormat::Standard), libc::STATX_SIZE | libc::STATX_MTIME);
+        assert_eq!(json_print_mask & libc::STATX_ATIME, 0);
+        assert_ne!(json_print_mask & libc::STATX_SIZE, 0);
+        assert_ne!(ls_mask & libc::STATX_INO, 0);
+        assert_eq!(SortBy::Name.stat_mask(), 0);
+        assert_eq!(SortBy::ModificationTime.stat_mask(), libc::STATX_MTIME);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn directory_entries_are_read_a_batch_at_a_time() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        for i in 0..1000 {
+            temp.child(format!("{:0>200}", i)).touch()?;
+        }
+
+        // Act
+        let first_batch = Directory::open(temp.path())?.read_batch()?;
+        let mut entries = DirectoryEntries::new(Arc::new(Directory::open(temp.path())?));
+        let first = entries.next().transpose()?;
+        // what is left is read before the directory is closed
+        entries.read_all();
+        let rest: Vec<DirectoryEntry> = entries.collect::<io::Result<_>>()?;
+
+        // Assert
+        assert!(!first_batch.is_empty() && first_batch.len() < 1000);
+        assert!(first.is_some());
+        assert_eq!(rest.len(), 999);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn entry_metadata_is_fetched_once_and_follows_links_when_asked_to() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("file.txt").write_str("12345")?;
+        temp.child("link").symlink_to_file(temp.child("file.txt").path())?;
+        let directory = Arc::new(Directory::open(temp.path())?);
+
+        // Act
+        let entry = Entry::found(Arc::clone(&directory), temp.path().join("file.txt"), 1, None, false, STAT_ALL);
+        let size_before = entry.stat().size;
+        temp.child("file.txt").write_str("1234567890")?;
+        let size_after = entry.stat().size;
+        let link = Entry::found(Arc::clone(&directory), temp.path().join("link"), 1, Some(FileType::Symlink), false, STAT_ALL);
+        let followed_link = Entry::found(Arc::clone(&directory), temp.path().join("link"), 1, Some(FileType::Symlink), true, STAT_ALL);
+
+        // Assert
+        assert_eq!(size_before, 5);
+        // the size was cached the first time it was asked for
+        assert_eq!(size_after, 5);
+        assert_eq!(link.file_type()?, FileType::Symlink);
+        assert_eq!(link.link_target(), Some(temp.child("file.txt").path()));
+        assert_eq!(followed_link.link_type()?, FileType::Symlink);
+        assert_eq!(followed_link.file_type()?, FileType::File);
+        assert_eq!(followed_link.stat().size, 10);
 
-        assert_eq!(eval(tokens, Arc::new(searcher)), expected);
+        // Teardown
         Ok(())
     }
 
     #[test]
-    fn regular_expression_works() -> Result<(), Box<dyn std::error::Error>> {
+    fn the_longest_link_target_is_read_whole() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
         let temp = assert_fs::TempDir::new()?;
-        std::env::set_current_dir(temp.path())?;
+        // the longest target a link can have, which fills the buffer it is first read into less one byte
+        let target = format!("{}/", "x".repeat(255)).repeat(16)[..libc::PATH_MAX as usize - 1].to_string();
+        temp.child("link").symlink_to_file(&target)?;
+        let directory = Directory::open(temp.path())?;
+
+        // Act
+        let read_target = directory.read_link_at(OsStr::new("link"))?;
+
+        // Assert
+        assert_eq!(read_target, PathBuf::from(&target));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn json_stays_valid_for_names_that_are_not_utf8() {
+        assert_eq!(json_string(b"plain.txt"), "\"plain.txt\"");
+        assert_eq!(json_string(b"a \"quoted\"\\name\n"), "\"a \\\"quoted\\\"\\\\name\\n\"");
+        assert_eq!(json_string(b"bad\xffname\xc3"), "\"bad\u{fffd}name\u{fffd}\"");
+        assert_eq!(json_bytes(b"bad\xff"), "[98,97,100,255]");
+    }
+
+    #[test_case("--false", "--false", false ; "Expect false when both operands are false")]
+    #[test_case("--false", "--true", false ; "Expect false when first operand is false and second operand is true")]
+    #[test_case("--true", "--false", false ; "Expect false when first operand is true and second operand is false")]
+    #[test_case("--true", "--true", true ; "Expect true when both operands are true")]
+    fn check_and_operator_works(first_operand: &str, second_operand: &str, expected: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new().with_directory("/tree"));
 
         let params = Params {
             symlink_setting: SymLinkSetting::Never,
             debug_opts: None,
-            optimisation_level: None
+            optimisation_level: None,
+            ..Default::default()
         };
+        let searcher = Searcher::new(params, None, None, "/tree".to_string(), None).with_file_system(file_system);
 
-        let searcher = Searcher::new(params, None, None, temp.path().to_str().unwrap().to_string(), None);
+        let operator = format!("--and");
+        let tokens = [ first_operand.to_owned(), operator, second_operand.to_owned() ].to_vec();
+        let predicate = Registry::new().parse(&tokens, None)?;
 
-        let file = NamedTempFile::new()?;
-        let file_name_with_extension = file.path().file_name().unwrap().to_str().unwrap().to_string();
-        
-        let tokens = [format!("--regex"), format!("*{}", file_name_with_extension)].to_vec();
+        let (_, logs) = search_to_end(searcher, Test::Custom(predicate));
+        assert_eq!(stdout_paths(&logs) == vec!["/tree"], expected);
+        Ok(())
+    }
+
+    #[test_case("--false", "--false", false ; "Expect false when both operands are false")]
+    #[test_case("--false", "--true", true ; "Expect true when first operand is false and second operand is true")]
+    #[test_case("--true", "--false", true ; "Expect true when first operand is true and second operand is false")]
+    #[test_case("--true", "--true", true ; "Expect true when both operands are true")]
+    fn check_or_operator_works(first_operand: &str, second_operand: &str, expected: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new().with_directory("/tree"));
+
+        let params = Params {
+            symlink_setting: SymLinkSetting::Never,
+            debug_opts: None,
+            optimisation_level: None,
+            ..Default::default()
+        };
+        let searcher = Searcher::new(params, None, None, "/tree".to_string(), None).with_file_system(file_system);
+
+        let operator = format!("--or");
+        let tokens = [ first_operand.to_owned(), operator, second_operand.to_owned() ].to_vec();
+        let predicate = Registry::new().parse(&tokens, None)?;
+
+        let (_, logs) = search_to_end(searcher, Test::Custom(predicate));
+        assert_eq!(stdout_paths(&logs) == vec!["/tree"], expected);
+        Ok(())
+    }
+
+    #[test_case("--true", false ; "Expect false when operand is true")]
+    #[test_case("--false", true ; "Expect true when operand is false")]
+    fn check_not_operator_works(operand: &str, expected: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new().with_directory("/tree"));
 
-        assert!(eval(tokens, Arc::new(searcher)));
+        let params = Params {
+            symlink_setting: SymLinkSetting::Never,
+            debug_opts: None,
+            optimisation_level: None,
+            ..Default::default()
+        };
+        let searcher = Searcher::new(params, None, None, "/tree".to_string(), None).with_file_system(file_system);
+
+        let operator = format!("--not");
+        let tokens = [operator, operand.to_owned()].to_vec();
+        let predicate = Registry::new().parse(&tokens, None)?;
+
+        let (_, logs) = search_to_end(searcher, Test::Custom(predicate));
+        assert_eq!(stdout_paths(&logs) == vec!["/tree"], expected);
+        Ok(())
+    }
+
+    #[test_case(&["--name", "a.txt", "--or", "--name", "b.rs"], &["/tree/a.txt", "/tree/b.rs"] ; "Expect either name to match")]
+    #[test_case(&["--type", "f", "--not", "--name", "a.txt"], &["/tree/b.rs", "/tree/sub/c.txt"] ; "Expect --not to bind tighter than the --and that is left out")]
+    #[test_case(&["--name", "a.txt", "--or", "--name", "sub", "--type", "f"], &["/tree/a.txt"] ; "Expect --and to bind tighter than --or")]
+    #[test_case(&["(", "--name", "a.txt", "--or", "--name", "sub", ")", "--type", "d"], &["/tree/sub"] ; "Expect parentheses to group an --or")]
+    fn expression_operators_combine_tests(expression: &[&str], expected: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new()
+            .with_file("/tree/a.txt", "")
+            .with_file("/tree/b.rs", "")
+            .with_file("/tree/sub/c.txt", ""));
+        let searcher = Searcher::new(Params::default(), None, None, "/tree".to_string(), None).with_file_system(file_system);
+        let tokens: Vec<String> = expression.iter().map(|token| token.to_string()).collect();
+
+        // Act
+        let predicate = Registry::new().parse(&tokens, None)?;
+        let (_, logs) = search_to_end(searcher, Test::Custom(predicate));
+
+        // Assert
+        assert_eq!(stdout_paths(&logs), expected);
 
+        // Teardown
+        Ok(())
+    }
+
+    #[test_case(&["(", "--name", "a.txt"], "( is not closed by a )" ; "Expect an error for an unclosed parenthesis")]
+    #[test_case(&["--name", "a.txt", ")"], "unexpected ) in the expression" ; "Expect an error for a parenthesis that is not opened")]
+    #[test_case(&["--name"], "--name expects a file name, but found nothing" ; "Expect an error for a test without its argument")]
+    #[test_case(&["--name", "a.txt", "--or"], "the expression ends where a test was expected" ; "Expect an error for an operator without an operand")]
+    #[test_case(&["--larger"], "unknown test --larger" ; "Expect an error for a test that is not registered")]
+    fn expressions_that_can_not_be_parsed_are_errors(expression: &[&str], expected: &str) {
+        let tokens: Vec<String> = expression.iter().map(|token| token.to_string()).collect();
+
+        let parsed = Registry::new().parse(&tokens, None);
+
+        assert!(parsed.is_err_and(|error| error == expected));
+    }
+
+    #[test_case(".*/regular_expression_works\\.txt", true ; "Expect a match when the whole path matches")]
+    #[test_case("regular_expression_works\\.txt", false ; "Expect no match when only the name matches")]
+    #[test_case(".*/regular_expression\\.txt", false ; "Expect no match when the path does not match")]
+    fn regular_expression_works(regex: &str, expected: bool) -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let file_system: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::new().with_file("/tree/regular_expression_works.txt", ""));
+        let params = Params {
+            symlink_setting: SymLinkSetting::Never,
+            debug_opts: None,
+            optimisation_level: None,
+            ..Default::default()
+        };
+        let searcher = Searcher::new(params, None, None, "/tree".to_string(), None).with_file_system(file_system);
+
+        // Act
+        let (_, logs) = search_to_end(searcher, Test::Regex(regex.to_string()));
+
+        // Assert
+        let stdout_logs = logs.get_logs_by_file_descriptor(FileDescriptor::StdOut);
+        assert_eq!(TestLogger::get_lines_from_logs_where_logs_contains_provided_value(stdout_logs.clone(), "/tree/regular_expression_works.txt".to_string()), expected,
+            "expected {} to match: {}. Full logs: \n{:#?}", regex, expected, stdout_logs);
+        assert!(logs.get_logs_by_file_descriptor(FileDescriptor::StdErr).is_empty());
+
+        // Teardown
         Ok(())
     }
 }
@@ -30,6 +30,8 @@ pub mod main {
     pub mod standardlogger;
     pub mod testlogger;
     pub mod test;
+    pub mod predicate;
+    pub mod expression;
     pub mod filedescriptor;
     pub mod debugopts;
     pub mod message;
@@ -38,5 +40,23 @@ pub mod main {
     pub mod worker;
     pub mod fnbox;
     pub mod job;
-    pub mod multithreadmessage;
+    pub mod action;
+    pub mod directory;
+    pub mod filesystem;
+    pub mod entry;
+    pub mod format;
+    pub mod outputfiles;
+    pub mod field;
+    pub mod printf;
+    pub mod outputformat;
+    pub mod jsonlogger;
+    pub mod csvlogger;
+    pub mod sink;
+    pub mod order;
+    pub mod filetype;
+    pub mod errorcounts;
+    pub mod stop;
+    pub mod searchbuilder;
+    #[cfg(feature = "async")]
+    pub mod entrystream;
 }
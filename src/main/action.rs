@@ -0,0 +1,38 @@
+pub use self::action::Action;
+pub mod action {
+    use crate::main::field::Field;
+    use crate::main::filedescriptor::FileDescriptor;
+    use crate::main::outputformat::OutputFormat;
+    use crate::main::printf::Printf;
+    // actions that write something take the file to write it to, so that e.g. --print and
+    // --fprint are the same action
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Action {
+        Print(FileDescriptor),
+        Print0(FileDescriptor),
+        Printf(FileDescriptor, Printf),
+        Ls(FileDescriptor),
+        Delete,
+        // hands the entry itself to the logger, without fetching anything for it, for searches
+        // that are run from a library
+        Send
+    }
+
+    // what --ls writes, and what --format json writes for each match
+    const LS_FIELDS: [Field; 8] = [Field::Inode, Field::Blocks, Field::Mode, Field::Links, Field::Owner, Field::Group, Field::Size, Field::ModificationTime];
+    const JSON_FIELDS: [Field; 6] = [Field::Type, Field::Size, Field::Permissions, Field::Uid, Field::Gid, Field::ModificationTime];
+
+    impl Action {
+        /// The `statx` fields that the action writes, which are fetched for what it is applied
+        /// to. The columns of --format csv and tsv are not known here, and have to be added to it.
+        pub fn stat_mask(&self, output_format: &OutputFormat) -> u32 {
+            let fields: &[Field] = match (self, output_format) {
+                (Action::Print(_), OutputFormat::Json) => &JSON_FIELDS,
+                (Action::Ls(_), _) => &LS_FIELDS,
+                (Action::Printf(_, printf), _) => return printf.stat_mask(),
+                _ => &[]
+            };
+            fields.iter().fold(0, |mask, field| mask | field.stat_mask())
+        }
+    }
+}
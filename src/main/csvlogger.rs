@@ -0,0 +1,71 @@
+pub use self::csvlogger::CsvLogger;
+pub mod csvlogger {
+    use crate::main::field::Field;
+    use crate::main::filedescriptor::FileDescriptor;
+    use crate::main::format::format_time;
+    use crate::main::line::Line;
+    use crate::main::logger::Logger;
+    use crate::main::message::Message;
+    use crate::main::standardlogger::{write_to_file_descriptor, StandardLogger};
+
+    /// Writes every match as a row of delimiter separated values, quoted as described by RFC 4180.
+    /// Errors and anything else that is not a match are written unchanged.
+    #[derive(Debug)]
+    pub struct CsvLogger {
+        separator: char,
+        columns: Vec<Field>
+    }
+
+    impl CsvLogger {
+        pub fn new(separator: char, columns: Vec<Field>) -> CsvLogger {
+            CsvLogger {
+                separator,
+                columns
+            }
+        }
+
+        /// Writes the names of the columns to stdout. Call it before the search starts, so that the
+        /// header is there even when nothing matches.
+        pub fn write_header(&self) {
+            let header = self.row(self.columns.iter().map(|column| column.name().to_string()).collect());
+            write_to_file_descriptor(Some(FileDescriptor::StdOut), header.as_bytes());
+        }
+
+        fn quote(&self, value: &str) -> String {
+            if value.contains([self.separator, '"', '\r', '\n']) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            }
+            else {
+                value.to_string()
+            }
+        }
+
+        fn row(&self, values: Vec<String>) -> String {
+            let values: Vec<String> = values.iter().map(|value| self.quote(value)).collect();
+            // RFC 4180 ends every row with CRLF, while tab separated values end with a newline
+            let terminator = match self.separator {
+                ',' => "\r\n",
+                _ => "\n"
+            };
+            format!("{}{}", values.join(&self.separator.to_string()), terminator)
+        }
+    }
+
+    impl Logger for CsvLogger {
+        fn log(&mut self, line: Line) {
+            let Message::Entry(entry) = &line.message else {
+                return StandardLogger::new().log(line);
+            };
+            let values = self.columns.iter().map(|column| match column.seconds(entry) {
+                // a format that spreadsheets recognise as a date
+                Some(seconds) => format_time(seconds, "%Y-%m-%d %H:%M:%S"),
+                None => column.render(entry)
+            }).collect();
+            write_to_file_descriptor(line.file_descriptor, self.row(values).as_bytes());
+        }
+
+        fn log_as_tree(&mut self, dir_entries: Vec<(String, bool)>, preceding_str: Option<String>) -> Vec<String> {
+            StandardLogger::new().log_as_tree(dir_entries, preceding_str)
+        }
+    }
+}
@@ -0,0 +1,365 @@
+pub use self::directory::{Directory, DirectoryEntries, DirectoryEntry};
+pub mod directory {
+    use std::collections::VecDeque;
+    use std::ffi::{CString, OsStr, OsString};
+    use std::io;
+    use std::mem::MaybeUninit;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::main::entry::Stat;
+    #[cfg(target_os = "linux")]
+    use crate::main::entry::STAT_TYPE;
+    use crate::main::filesystem::FileSystem;
+    use crate::main::filetype::FileType;
+
+    /// An open handle to a directory. Children are opened and unlinked relative to this handle
+    /// rather than by path, so a path component that is swapped for a symlink while the search
+    /// is running cannot redirect the operation to somewhere outside of the tree.
+    #[derive(Debug)]
+    pub struct Directory {
+        handle: Handle
+    }
+
+    #[derive(Debug)]
+    enum Handle {
+        Fd(OwnedFd),
+        // a directory of a file system other than that of the OS, which is only read by its path.
+        // Its entries are read in one batch, and `read` is set once they have been
+        Path { file_system: Arc<dyn FileSystem>, path: PathBuf, read: AtomicBool }
+    }
+
+    /// An entry as it was read from a directory, without a stat call.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct DirectoryEntry {
+        pub name: OsString,
+        pub ino: u64,
+        // `None` when the file system does not say, in which case the entry has to be stat'ed
+        pub file_type: Option<FileType>
+    }
+
+    // the number of directories that are open across every thread
+    static OPEN: AtomicUsize = AtomicUsize::new(0);
+
+    impl Directory {
+        pub fn open_count() -> usize {
+            OPEN.load(Ordering::Relaxed)
+        }
+
+        /// Whether this holds on to a file descriptor, rather than being read by its path.
+        pub fn has_descriptor(&self) -> bool {
+            matches!(self.handle, Handle::Fd(_))
+        }
+
+        pub fn open(path: &Path) -> io::Result<Directory> {
+            Directory::open_path(path, true)
+        }
+
+        /// Opens a directory by its path. Unless `follow_symlink` is set, this fails if the last component is a symlink.
+        pub fn open_path(path: &Path, follow_symlink: bool) -> io::Result<Directory> {
+            let path = to_c_string(path.as_os_str())?;
+            let nofollow = if follow_symlink { 0 } else { libc::O_NOFOLLOW };
+            let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY | nofollow | libc::O_CLOEXEC) };
+            Directory::from_result(fd)
+        }
+
+        /// Opens a directory of `file_system` by its path. Unless `follow_symlink` is set, this fails
+        /// if the last component is a symlink.
+        pub fn open_in(file_system: Arc<dyn FileSystem>, path: &Path, follow_symlink: bool) -> io::Result<Directory> {
+            let file_type = FileType::from_mode(file_system.metadata(path, follow_symlink)?.mode);
+            match file_type {
+                FileType::Directory => Ok(Directory { handle: Handle::Path { file_system, path: path.to_path_buf(), read: AtomicBool::new(false) } }),
+                // as open does with O_NOFOLLOW
+                FileType::Symlink => Err(io::Error::from_raw_os_error(libc::ELOOP)),
+                _ => Err(io::Error::from_raw_os_error(libc::ENOTDIR))
+            }
+        }
+
+        /// Opens the child directory `name`. Unless `follow_symlink` is set, this fails if it is a symlink.
+        pub fn open_at(&self, name: &OsStr, follow_symlink: bool) -> io::Result<Directory> {
+            let fd = match &self.handle {
+                Handle::Fd(fd) => fd,
+                Handle::Path { file_system, path, .. } => return Directory::open_in(Arc::clone(file_system), &path.join(name), follow_symlink)
+            };
+            let name = to_c_string(name)?;
+            let nofollow = if follow_symlink { 0 } else { libc::O_NOFOLLOW };
+            let fd = unsafe { libc::openat(fd.as_raw_fd(), name.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY | nofollow | libc::O_CLOEXEC) };
+            Directory::from_result(fd)
+        }
+
+        /// Removes the entry `name` from this directory. Directories are only removed when they are empty.
+        pub fn unlink_at(&self, name: &OsStr, is_dir: bool) -> io::Result<()> {
+            let fd = match &self.handle {
+                Handle::Fd(fd) => fd,
+                Handle::Path { file_system, path, .. } => return file_system.remove(&path.join(name), is_dir)
+            };
+            let name = to_c_string(name)?;
+            let flags = if is_dir { libc::AT_REMOVEDIR } else { 0 };
+            match unsafe { libc::unlinkat(fd.as_raw_fd(), name.as_ptr(), flags) } {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(())
+            }
+        }
+
+        pub fn stat_at(&self, name: &OsStr, follow_symlink: bool) -> io::Result<Stat> {
+            let fd = match &self.handle {
+                Handle::Fd(fd) => fd,
+                Handle::Path { file_system, path, .. } => return file_system.metadata(&path.join(name), follow_symlink)
+            };
+            let name = to_c_string(name)?;
+            let flags = if follow_symlink { 0 } else { libc::AT_SYMLINK_NOFOLLOW };
+            let mut stat = MaybeUninit::<libc::stat>::uninit();
+            match unsafe { libc::fstatat(fd.as_raw_fd(), name.as_ptr(), stat.as_mut_ptr(), flags) } {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(Stat::from(unsafe { &stat.assume_init() }))
+            }
+        }
+
+        pub fn stat(&self) -> io::Result<Stat> {
+            let fd = match &self.handle {
+                Handle::Fd(fd) => fd,
+                Handle::Path { file_system, path, .. } => return file_system.metadata(path, true)
+            };
+            let mut stat = MaybeUninit::<libc::stat>::uninit();
+            match unsafe { libc::fstat(fd.as_raw_fd(), stat.as_mut_ptr()) } {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(Stat::from(unsafe { &stat.assume_init() }))
+            }
+        }
+
+        /// Fetches the type and mode of the entry `name`, and the fields in `mask`, such as
+        /// `STATX_SIZE`. Fields that were not asked for may be left as zero.
+        pub fn stat_entry(&self, name: &OsStr, follow_symlink: bool, mask: u32) -> io::Result<Stat> {
+            match self.statx_at(name, follow_symlink, mask) {
+                Err(error) if error.raw_os_error() == Some(libc::ENOSYS) => self.stat_at(name, follow_symlink),
+                result => result
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        fn statx_at(&self, name: &OsStr, follow_symlink: bool, mask: u32) -> io::Result<Stat> {
+            let Handle::Fd(fd) = &self.handle else {
+                return Err(io::Error::from_raw_os_error(libc::ENOSYS));
+            };
+            let name = to_c_string(name)?;
+            let flags = if follow_symlink { 0 } else { libc::AT_SYMLINK_NOFOLLOW };
+            let mask = mask | STAT_TYPE | libc::STATX_MODE;
+            let mut statx = MaybeUninit::<libc::statx>::uninit();
+            match unsafe { libc::statx(fd.as_raw_fd(), name.as_ptr(), flags | libc::AT_STATX_SYNC_AS_STAT, mask, statx.as_mut_ptr()) } {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(Stat::from(unsafe { &statx.assume_init() }))
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        fn statx_at(&self, _name: &OsStr, _follow_symlink: bool, _mask: u32) -> io::Result<Stat> {
+            Err(io::Error::from_raw_os_error(libc::ENOSYS))
+        }
+
+        pub fn read_link_at(&self, name: &OsStr) -> io::Result<PathBuf> {
+            let fd = match &self.handle {
+                Handle::Fd(fd) => fd,
+                Handle::Path { file_system, path, .. } => return file_system.read_link(&path.join(name))
+            };
+            let name = to_c_string(name)?;
+            let mut buffer = vec![0u8; libc::PATH_MAX as usize];
+            loop {
+                match unsafe { libc::readlinkat(fd.as_raw_fd(), name.as_ptr(), buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) } {
+                    -1 => return Err(io::Error::last_os_error()),
+                    // the target may have been cut short to fit, so read it again with more room, as
+                    // std::fs::read_link does
+                    length if length as usize == buffer.len() => buffer.resize(buffer.len() * 2, 0),
+                    length => {
+                        buffer.truncate(length as usize);
+                        return Ok(PathBuf::from(OsString::from_vec(buffer)));
+                    }
+                }
+            }
+        }
+
+        /// Reads the next entries other than `.` and `..`, as many as one read of the directory
+        /// gives. None are left once this gives an empty batch.
+        pub fn read_batch(&self) -> io::Result<Vec<DirectoryEntry>> {
+            match &self.handle {
+                Handle::Fd(fd) => read_entries(fd),
+                Handle::Path { file_system, path, read } => match read.swap(true, Ordering::Relaxed) {
+                    true => Ok(Vec::new()),
+                    false => file_system.read_dir(path)
+                }
+            }
+        }
+
+        /// Reads every entry other than `.` and `..`. This can only be done once for each handle.
+        pub fn entries(&self) -> io::Result<Vec<DirectoryEntry>> {
+            let mut entries = Vec::new();
+            loop {
+                let batch = self.read_batch()?;
+                if batch.is_empty() {
+                    return Ok(entries);
+                }
+                entries.extend(batch);
+            }
+        }
+
+        fn from_result(fd: libc::c_int) -> io::Result<Directory> {
+            match fd {
+                -1 => Err(io::Error::last_os_error()),
+                fd => {
+                    OPEN.fetch_add(1, Ordering::Relaxed);
+                    Ok(Directory { handle: Handle::Fd(unsafe { OwnedFd::from_raw_fd(fd) }) })
+                }
+            }
+        }
+    }
+
+    impl Drop for Directory {
+        fn drop(&mut self) {
+            if let Handle::Fd(_) = self.handle {
+                OPEN.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// The entries of a directory, which are read a batch at a time as they are needed, so that
+    /// the search of a huge directory can be stopped part of the way through it. An error ends
+    /// the entries.
+    #[derive(Debug, Default)]
+    pub struct DirectoryEntries {
+        // `None` once every entry has been read
+        directory: Option<Arc<Directory>>,
+        batch: VecDeque<DirectoryEntry>,
+        error: Option<io::Error>
+    }
+
+    impl DirectoryEntries {
+        pub fn new(directory: Arc<Directory>) -> DirectoryEntries {
+            DirectoryEntries { directory: Some(directory), batch: VecDeque::new(), error: None }
+        }
+
+        /// Whether there are entries left, which reads the next batch if the last one is used up.
+        pub fn has_more(&mut self) -> bool {
+            while self.batch.is_empty() && self.directory.is_some() {
+                self.read_batch();
+            }
+            !self.batch.is_empty() || self.error.is_some()
+        }
+
+        /// Reads the entries that are left, so that the directory does not have to be kept open for them.
+        pub fn read_all(&mut self) {
+            while self.directory.is_some() {
+                self.read_batch();
+            }
+        }
+
+        fn read_batch(&mut self) {
+            let Some(directory) = &self.directory else {
+                return;
+            };
+            match directory.read_batch() {
+                Ok(batch) if !batch.is_empty() => self.batch.extend(batch),
+                Ok(_) => self.directory = None,
+                Err(error) => {
+                    self.error = Some(error);
+                    self.directory = None;
+                }
+            }
+        }
+    }
+
+    impl Iterator for DirectoryEntries {
+        type Item = io::Result<DirectoryEntry>;
+
+        fn next(&mut self) -> Option<io::Result<DirectoryEntry>> {
+            self.has_more();
+            match self.batch.pop_front() {
+                Some(entry) => Some(Ok(entry)),
+                None => self.error.take().map(Err)
+            }
+        }
+    }
+
+    // Reads entries until it has some or there are none left, as `.` and `..` can be all there is
+    // in a read.
+    #[cfg(target_os = "linux")]
+    fn read_entries(fd: &OwnedFd) -> io::Result<Vec<DirectoryEntry>> {
+        // struct linux_dirent64 { u64 d_ino; i64 d_off; u16 d_reclen; u8 d_type; char d_name[]; }
+        const NAME_OFFSET: usize = 19;
+        let mut entries = Vec::new();
+        // u64s, to keep the records aligned
+        let mut buffer = vec![0u64; 4096];
+        loop {
+            let read = unsafe { libc::syscall(libc::SYS_getdents64, fd.as_raw_fd(), buffer.as_mut_ptr(), buffer.len() * 8) };
+            if read == -1 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error);
+            }
+            if read == 0 {
+                return Ok(entries);
+            }
+            let bytes = unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const u8, read as usize) };
+            let mut offset = 0;
+            while offset < bytes.len() {
+                let record = &bytes[offset..];
+                let record_length = u16::from_ne_bytes([record[16], record[17]]) as usize;
+                let name = &record[NAME_OFFSET..record_length];
+                let name = &name[..name.iter().position(|byte| *byte == 0).unwrap_or(name.len())];
+                if name != b"." && name != b".." {
+                    entries.push(DirectoryEntry {
+                        name: OsStr::from_bytes(name).to_os_string(),
+                        ino: u64::from_ne_bytes(record[0..8].try_into().unwrap()),
+                        file_type: FileType::from_d_type(record[18])
+                    });
+                }
+                offset += record_length;
+            }
+            if !entries.is_empty() {
+                return Ok(entries);
+            }
+        }
+    }
+
+    // Reads every entry at once. The offset of the directory is left at its end, so the next read
+    // gives nothing.
+    #[cfg(not(target_os = "linux"))]
+    fn read_entries(fd: &OwnedFd) -> io::Result<Vec<DirectoryEntry>> {
+        // closedir closes the descriptor that it is given, so it gets a copy
+        let fd = unsafe { libc::dup(fd.as_raw_fd()) };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let dir = unsafe { libc::fdopendir(fd) };
+        if dir.is_null() {
+            let error = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(error);
+        }
+        let mut entries = Vec::new();
+        loop {
+            let entry = unsafe { libc::readdir(dir) };
+            if entry.is_null() {
+                break;
+            }
+            let entry = unsafe { &*entry };
+            let name = unsafe { std::ffi::CStr::from_ptr(entry.d_name.as_ptr()) }.to_bytes();
+            if name != b"." && name != b".." {
+                entries.push(DirectoryEntry {
+                    name: OsStr::from_bytes(name).to_os_string(),
+                    ino: entry.d_ino as u64,
+                    file_type: FileType::from_d_type(entry.d_type)
+                });
+            }
+        }
+        unsafe { libc::closedir(dir) };
+        Ok(entries)
+    }
+
+    fn to_c_string(value: &OsStr) -> io::Result<CString> {
+        CString::new(value.as_bytes()).map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+    }
+}
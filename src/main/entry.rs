@@ -0,0 +1,296 @@
+pub use self::entry::{Entry, Stat, STAT_TYPE, STAT_ALL};
+pub mod entry {
+    use std::ffi::OsStr;
+    use std::fmt;
+    use std::fs::{self, Metadata};
+    use std::io;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, OnceLock};
+
+    use crate::main::directory::Directory;
+    use crate::main::filesystem::FileSystem;
+    use crate::main::filetype::FileType;
+
+    /// The `statx` mask for the type of a file, and for everything that an action can print.
+    #[cfg(target_os = "linux")]
+    pub const STAT_TYPE: u32 = libc::STATX_TYPE;
+    #[cfg(target_os = "linux")]
+    pub const STAT_ALL: u32 = libc::STATX_BASIC_STATS | libc::STATX_BTIME;
+    #[cfg(not(target_os = "linux"))]
+    pub const STAT_TYPE: u32 = 0;
+    #[cfg(not(target_os = "linux"))]
+    pub const STAT_ALL: u32 = 0;
+
+    /// A file that was found by the searcher. Its metadata is only fetched the first time that a
+    /// test or an action asks for it, with a single `statx` call for the fields in `mask`.
+    #[derive(Clone)]
+    pub struct Entry {
+        pub path: PathBuf,
+        pub depth: u32,
+        // the directory that the entry was read from, which metadata is fetched relative to
+        parent: Option<Arc<Directory>>,
+        follow_symlink: bool,
+        mask: u32,
+        // the type from the directory entry, when the file system gives it
+        d_type: Option<FileType>,
+        stat: OnceLock<Result<Stat, i32>>,
+        link_target: OnceLock<Option<PathBuf>>
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Default)]
+    pub struct Stat {
+        pub dev: u64,
+        pub ino: u64,
+        pub mode: u32,
+        pub nlink: u64,
+        pub uid: u32,
+        pub gid: u32,
+        pub size: u64,
+        // number of 512 byte blocks allocated
+        pub blocks: u64,
+        pub atime: i64,
+        pub mtime: i64,
+        pub ctime: i64,
+        // not every file system records when a file was created
+        pub btime: Option<i64>
+    }
+
+    impl Entry {
+        /// Fetches the metadata of the file straight away, without following it if it is a symlink.
+        pub fn new(path: &Path, depth: u32) -> io::Result<Entry> {
+            let metadata = fs::symlink_metadata(path)?;
+            let link_target = match metadata.file_type().is_symlink() {
+                true => Some(fs::read_link(path)?),
+                false => None
+            };
+            Ok(Entry {
+                path: path.to_path_buf(),
+                depth,
+                parent: None,
+                follow_symlink: false,
+                mask: 0,
+                d_type: None,
+                stat: OnceLock::from(Ok(Stat::from(&metadata))),
+                link_target: OnceLock::from(link_target)
+            })
+        }
+
+        /// A starting point of `file_system`, at depth 0. When `follow_symlink` is set, its metadata
+        /// is that of the file a symlink points to, unless the symlink is broken.
+        pub fn starting_point(path: &Path, follow_symlink: bool, file_system: &dyn FileSystem) -> io::Result<Entry> {
+            let stat = file_system.metadata(path, false)?;
+            let is_symlink = FileType::from_mode(stat.mode).is_symlink();
+            let followed = match follow_symlink && is_symlink {
+                true => file_system.metadata(path, true).ok(),
+                false => None
+            };
+            let link_target = match is_symlink && followed.is_none() {
+                true => Some(file_system.read_link(path)?),
+                false => None
+            };
+            Ok(Entry {
+                path: path.to_path_buf(),
+                depth: 0,
+                parent: None,
+                follow_symlink,
+                mask: 0,
+                d_type: Some(FileType::from_mode(stat.mode)),
+                stat: OnceLock::from(Ok(followed.unwrap_or(stat))),
+                link_target: OnceLock::from(link_target)
+            })
+        }
+
+        /// An entry that was read from `parent`, and that has not been stat'ed yet. When
+        /// `follow_symlink` is set, the metadata is that of the file a symlink points to.
+        pub fn found(parent: Arc<Directory>, path: PathBuf, depth: u32, d_type: Option<FileType>, follow_symlink: bool, mask: u32) -> Entry {
+            Entry {
+                path,
+                depth,
+                parent: Some(parent),
+                follow_symlink,
+                mask,
+                d_type,
+                stat: OnceLock::new(),
+                link_target: OnceLock::new()
+            }
+        }
+
+        pub fn name(&self) -> &OsStr {
+            self.path.file_name().unwrap_or(self.path.as_os_str())
+        }
+
+        /// The path below the starting point that the entry was found in, which is made up of as
+        /// many components as the entry is deep.
+        pub fn relative_path(&self) -> PathBuf {
+            let components: Vec<_> = self.path.components().collect();
+            components[components.len().saturating_sub(self.depth as usize)..].iter().collect()
+        }
+
+        /// The type of the entry itself, even if it is a symlink that is being followed.
+        pub fn link_type(&self) -> io::Result<FileType> {
+            match (self.d_type, &self.parent) {
+                (Some(d_type), _) => Ok(d_type),
+                (None, Some(parent)) if self.follow_symlink => parent.stat_at(self.name(), false).map(|stat| FileType::from_mode(stat.mode)),
+                _ => self.try_stat().map(|stat| FileType::from_mode(stat.mode))
+            }
+        }
+
+        /// The type of the file, which is that of the file a symlink points to when following symlinks,
+        /// unless the symlink is broken.
+        pub fn file_type(&self) -> io::Result<FileType> {
+            match self.d_type {
+                Some(d_type) if !(d_type.is_symlink() && self.follow_symlink) => Ok(d_type),
+                _ => self.try_stat().map(|stat| FileType::from_mode(stat.mode))
+            }
+        }
+
+        pub fn try_stat(&self) -> io::Result<&Stat> {
+            self.stat.get_or_init(|| self.fetch().map_err(|error| error.raw_os_error().unwrap_or(libc::EIO)))
+                .as_ref()
+                .map_err(|errno| io::Error::from_raw_os_error(*errno))
+        }
+
+        /// The metadata of the entry, which is all zeros if it could not be fetched. Use `try_stat`
+        /// to find out why.
+        pub fn stat(&self) -> Stat {
+            self.try_stat().copied().unwrap_or_default()
+        }
+
+        pub fn link_target(&self) -> Option<&Path> {
+            self.link_target.get_or_init(|| {
+                match self.try_stat() {
+                    Ok(stat) if FileType::from_mode(stat.mode).is_symlink() => match &self.parent {
+                        Some(parent) => parent.read_link_at(self.name()).ok(),
+                        None => fs::read_link(&self.path).ok()
+                    },
+                    _ => None
+                }
+            }).as_deref()
+        }
+
+        /// A copy that no longer holds on to a descriptor of the directory it was found in. What has
+        /// not been fetched yet is fetched by its path, when it is asked for.
+        pub fn detached(&self) -> Entry {
+            Entry {
+                // a directory of a file system other than that of the OS holds no descriptor
+                parent: self.parent.as_ref().filter(|parent| !parent.has_descriptor()).cloned(),
+                ..self.clone()
+            }
+        }
+
+        fn fetch(&self) -> io::Result<Stat> {
+            let Some(parent) = &self.parent else {
+                let metadata = match self.follow_symlink {
+                    true => fs::metadata(&self.path).or_else(|_| fs::symlink_metadata(&self.path)),
+                    false => fs::symlink_metadata(&self.path)
+                };
+                return metadata.map(|metadata| Stat::from(&metadata));
+            };
+            match self.follow_symlink {
+                // a broken symlink is described by the symlink itself
+                true => parent.stat_entry(self.name(), true, self.mask).or_else(|_| parent.stat_entry(self.name(), false, self.mask)),
+                false => parent.stat_entry(self.name(), false, self.mask)
+            }
+        }
+    }
+
+    impl fmt::Debug for Entry {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Entry")
+                .field("path", &self.path)
+                .field("depth", &self.depth)
+                .field("stat", &self.stat.get())
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl PartialEq for Entry {
+        fn eq(&self, other: &Entry) -> bool {
+            self.path == other.path && self.depth == other.depth && self.stat.get() == other.stat.get()
+        }
+    }
+
+    impl From<&Metadata> for Stat {
+        fn from(metadata: &Metadata) -> Stat {
+            Stat {
+                dev: metadata.dev(),
+                ino: metadata.ino(),
+                mode: metadata.mode(),
+                nlink: metadata.nlink(),
+                uid: metadata.uid(),
+                gid: metadata.gid(),
+                size: metadata.size(),
+                blocks: metadata.blocks(),
+                atime: metadata.atime(),
+                mtime: metadata.mtime(),
+                ctime: metadata.ctime(),
+                btime: metadata.created().ok()
+                    .and_then(|created| created.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs() as i64)
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    impl From<&libc::stat> for Stat {
+        fn from(stat: &libc::stat) -> Stat {
+            Stat {
+                dev: stat.st_dev,
+                ino: stat.st_ino,
+                mode: stat.st_mode,
+                nlink: stat.st_nlink,
+                uid: stat.st_uid,
+                gid: stat.st_gid,
+                size: stat.st_size as u64,
+                blocks: stat.st_blocks as u64,
+                atime: stat.st_atime,
+                mtime: stat.st_mtime,
+                ctime: stat.st_ctime,
+                // fstat does not give it
+                btime: None
+            }
+        }
+    }
+
+    // the fields are not the same size on every OS
+    #[cfg(not(target_os = "linux"))]
+    impl From<&libc::stat> for Stat {
+        fn from(stat: &libc::stat) -> Stat {
+            Stat {
+                dev: stat.st_dev as u64,
+                ino: stat.st_ino as u64,
+                mode: stat.st_mode as u32,
+                nlink: stat.st_nlink as u64,
+                uid: stat.st_uid,
+                gid: stat.st_gid,
+                size: stat.st_size as u64,
+                blocks: stat.st_blocks as u64,
+                atime: stat.st_atime as i64,
+                mtime: stat.st_mtime as i64,
+                ctime: stat.st_ctime as i64,
+                btime: None
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    impl From<&libc::statx> for Stat {
+        fn from(statx: &libc::statx) -> Stat {
+            Stat {
+                dev: libc::makedev(statx.stx_dev_major, statx.stx_dev_minor),
+                ino: statx.stx_ino,
+                mode: u32::from(statx.stx_mode),
+                nlink: u64::from(statx.stx_nlink),
+                uid: statx.stx_uid,
+                gid: statx.stx_gid,
+                size: statx.stx_size,
+                blocks: statx.stx_blocks,
+                atime: statx.stx_atime.tv_sec,
+                mtime: statx.stx_mtime.tv_sec,
+                ctime: statx.stx_ctime.tv_sec,
+                btime: (statx.stx_mask & libc::STATX_BTIME != 0).then_some(statx.stx_btime.tv_sec)
+            }
+        }
+    }
+}
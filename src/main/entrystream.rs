@@ -0,0 +1,67 @@
+pub use self::entrystream::EntryStream;
+pub mod entrystream {
+    use std::pin::Pin;
+    use std::sync::mpsc::{Receiver, TryRecvError};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+
+    use futures_core::Stream;
+
+    use crate::main::entry::Entry;
+    use crate::main::errorcounts::ErrorCounts;
+    use crate::main::line::Line;
+    use crate::main::message::Message;
+    use crate::main::searcher::Searcher;
+    use crate::main::stop::StopReason;
+
+    /// The entries that a search finds, as a `Stream`, which `SearchBuilder::build_stream` makes.
+    /// The search runs on threads of its own, so polling never blocks. It waits for the stream
+    /// once enough entries are waiting to be taken from it.
+    #[derive(Debug)]
+    pub struct EntryStream {
+        receiver: Receiver<Line>,
+        // the task to wake when the search sends an entry or finishes
+        waker: Arc<Mutex<Option<Waker>>>,
+        searcher: Arc<Searcher>
+    }
+
+    impl EntryStream {
+        pub(crate) fn new(receiver: Receiver<Line>, waker: Arc<Mutex<Option<Waker>>>, searcher: Arc<Searcher>) -> EntryStream {
+            EntryStream { receiver, waker, searcher }
+        }
+
+        /// The errors that the search has run into so far, which the stream does not yield.
+        pub fn errors(&self) -> &ErrorCounts {
+            self.searcher.errors()
+        }
+    }
+
+    impl Stream for EntryStream {
+        type Item = Entry;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Entry>> {
+            let this = self.get_mut();
+            let mut registered = false;
+            loop {
+                match this.receiver.try_recv() {
+                    Ok(Line { message: Message::Entry(entry), .. }) => return Poll::Ready(Some(*entry)),
+                    Ok(_) => {}
+                    Err(TryRecvError::Disconnected) => return Poll::Ready(None),
+                    Err(TryRecvError::Empty) if registered => return Poll::Pending,
+                    // an entry that was sent before the waker was stored would not wake the task,
+                    // so the channel is checked once more after storing it
+                    Err(TryRecvError::Empty) => {
+                        *this.waker.lock().unwrap() = Some(cx.waker().clone());
+                        registered = true;
+                    }
+                }
+            }
+        }
+    }
+
+    impl Drop for EntryStream {
+        fn drop(&mut self) {
+            self.searcher.stop().stop(StopReason::LimitReached);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+pub use self::errorcounts::{ErrorCategory, ErrorCounts};
+pub mod errorcounts {
+    use std::fmt;
+    use std::io;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ErrorCategory {
+        Permission,
+        // removed or renamed between being read from its directory and being looked at
+        Vanished,
+        Loop,
+        Io
+    }
+
+    impl ErrorCategory {
+        /// The category of an error about something that was not read from a directory, such as a
+        /// starting point, which is not there if it is not found.
+        pub fn of(error: &io::Error) -> ErrorCategory {
+            match error.kind() {
+                io::ErrorKind::PermissionDenied => ErrorCategory::Permission,
+                _ if error.raw_os_error() == Some(libc::ELOOP) => ErrorCategory::Loop,
+                _ => ErrorCategory::Io
+            }
+        }
+
+        /// The category of an error about an entry that was read from a directory, which has
+        /// vanished if it is no longer found.
+        pub fn of_entry(error: &io::Error) -> ErrorCategory {
+            match error.kind() {
+                io::ErrorKind::NotFound => ErrorCategory::Vanished,
+                _ => ErrorCategory::of(error)
+            }
+        }
+    }
+
+    /// The number of errors of each category that occurred during a search. Any error makes the
+    /// search exit with a status of 1, as find does.
+    #[derive(Debug, Default)]
+    pub struct ErrorCounts {
+        permission: AtomicU64,
+        vanished: AtomicU64,
+        loops: AtomicU64,
+        io: AtomicU64
+    }
+
+    impl ErrorCounts {
+        pub fn record(&self, category: ErrorCategory) {
+            self.counter(category).fetch_add(1, Ordering::Relaxed);
+        }
+
+        pub fn count(&self, category: ErrorCategory) -> u64 {
+            self.counter(category).load(Ordering::Relaxed)
+        }
+
+        pub fn total(&self) -> u64 {
+            [ErrorCategory::Permission, ErrorCategory::Vanished, ErrorCategory::Loop, ErrorCategory::Io].iter()
+                .map(|category| self.count(*category))
+                .sum()
+        }
+
+        fn counter(&self, category: ErrorCategory) -> &AtomicU64 {
+            match category {
+                ErrorCategory::Permission => &self.permission,
+                ErrorCategory::Vanished => &self.vanished,
+                ErrorCategory::Loop => &self.loops,
+                ErrorCategory::Io => &self.io
+            }
+        }
+    }
+
+    // what --error-summary writes once the search has finished
+    impl fmt::Display for ErrorCounts {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "rfind: {} errors: {} permission denied, {} vanished, {} file system loops, {} I/O",
+                self.total(),
+                self.count(ErrorCategory::Permission),
+                self.count(ErrorCategory::Vanished),
+                self.count(ErrorCategory::Loop),
+                self.count(ErrorCategory::Io))
+        }
+    }
+}
@@ -0,0 +1,127 @@
+pub use self::expression::Registry;
+pub mod expression {
+    use std::collections::HashMap;
+    use std::iter::Peekable;
+    use std::slice::Iter;
+    use std::sync::Arc;
+
+    use crate::main::predicate::{All, Any, False, Name, Not, PathRegex, Predicate, True, Types};
+
+    /// The predicates that an expression can name on top of the built-in tests, e.g.
+    /// `Registry::new().with("--larger", Arc::new(Larger(1024)))` for `-- --name core --larger`.
+    /// A built-in test is not replaced by a predicate with the same name.
+    #[derive(Debug, Clone, Default)]
+    pub struct Registry {
+        predicates: HashMap<String, Arc<dyn Predicate>>
+    }
+
+    impl Registry {
+        pub fn new() -> Registry {
+            Registry { predicates: HashMap::new() }
+        }
+
+        pub fn with<N: Into<String>>(mut self, name: N, predicate: Arc<dyn Predicate>) -> Registry {
+            self.predicates.insert(name.into(), predicate);
+            self
+        }
+
+        pub fn get(&self, name: &str) -> Option<&Arc<dyn Predicate>> {
+            self.predicates.get(name)
+        }
+
+        /// Parses an expression into the predicate that the search checks. `--not` binds tighter
+        /// than `--and`, which can be left out, and `--and` binds tighter than `--or`, with `(` and
+        /// `)` to group them. The operands of an `--and` are checked from the cheapest to the most
+        /// expensive, unless the optimisation level is 0. Actions and `--prune` are always true, and
+        /// the arguments that they take are left to the command line to check.
+        pub fn parse(&self, tokens: &[String], optimisation_level: Option<u8>) -> Result<Arc<dyn Predicate>, String> {
+            if tokens.is_empty() {
+                return Ok(Arc::new(True));
+            }
+            let mut parser = Parser { tokens: tokens.iter().peekable(), registry: self, in_order: optimisation_level == Some(0) };
+            let predicate = parser.or()?;
+            match parser.tokens.next() {
+                Some(token) => Err(format!("unexpected {} in the expression", token)),
+                None => Ok(predicate)
+            }
+        }
+    }
+
+    struct Parser<'a> {
+        tokens: Peekable<Iter<'a, String>>,
+        registry: &'a Registry,
+        in_order: bool
+    }
+
+    impl Parser<'_> {
+        fn or(&mut self) -> Result<Arc<dyn Predicate>, String> {
+            let mut operands = vec![self.and()?];
+            while self.tokens.next_if(|token| *token == "--or").is_some() {
+                operands.push(self.and()?);
+            }
+            Ok(match operands.len() {
+                1 => operands.remove(0),
+                _ => Arc::new(Any(operands))
+            })
+        }
+
+        fn and(&mut self) -> Result<Arc<dyn Predicate>, String> {
+            let mut operands = vec![self.not()?];
+            loop {
+                match self.tokens.peek().map(|token| token.as_str()) {
+                    None | Some("--or") | Some(")") => break,
+                    Some("--and") => _ = self.tokens.next(),
+                    // two operands next to each other are joined by an --and
+                    Some(_) => {}
+                }
+                operands.push(self.not()?);
+            }
+            Ok(match (operands.len(), self.in_order) {
+                (1, _) => operands.remove(0),
+                (_, true) => Arc::new(All::in_order(operands)),
+                (_, false) => Arc::new(All::new(operands))
+            })
+        }
+
+        fn not(&mut self) -> Result<Arc<dyn Predicate>, String> {
+            match self.tokens.next_if(|token| *token == "--not") {
+                Some(_) => Ok(Arc::new(Not(self.not()?))),
+                None => self.operand()
+            }
+        }
+
+        fn operand(&mut self) -> Result<Arc<dyn Predicate>, String> {
+            let token = self.tokens.next().ok_or("the expression ends where a test was expected")?;
+            Ok(match token.as_str() {
+                "(" => {
+                    let predicate = self.or()?;
+                    if self.tokens.next_if(|token| *token == ")").is_none() {
+                        return Err("( is not closed by a )".to_string());
+                    }
+                    predicate
+                }
+                "--true" | "--prune" | "--print" | "--print0" | "--delete" | "--ls" => Arc::new(True),
+                "--false" => Arc::new(False),
+                "--name" => Arc::new(Name(self.argument(token, "a file name")?)),
+                "--type" => Arc::new(Types(self.argument(token, "a file type")?)),
+                "--regex" => Arc::new(PathRegex::new(&self.argument(token, "a regular expression")?)?),
+                "--printf" | "--fprint" | "--fprint0" | "--fls" => {
+                    self.tokens.next();
+                    Arc::new(True)
+                }
+                "--fprintf" => {
+                    self.tokens.nth(1);
+                    Arc::new(True)
+                }
+                name => match self.registry.get(name) {
+                    Some(predicate) => Arc::clone(predicate),
+                    None => return Err(format!("unknown test {}", name))
+                }
+            })
+        }
+
+        fn argument(&mut self, token: &str, expected: &str) -> Result<String, String> {
+            self.tokens.next().cloned().ok_or(format!("{} expects {}, but found nothing", token, expected))
+        }
+    }
+}
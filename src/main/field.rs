@@ -0,0 +1,174 @@
+pub use self::field::Field;
+pub mod field {
+    use std::os::unix::ffi::OsStrExt;
+
+    use crate::main::entry::Entry;
+    use crate::main::format::{format_time, group_name, mode_string, user_name};
+
+    /// A piece of information about an entry that can be written out, named by its `--printf` directive.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Field {
+        Path,
+        Name,
+        Directory,
+        RelativePath,
+        Depth,
+        Size,
+        Blocks,
+        KiloBlocks,
+        Permissions,
+        Mode,
+        Owner,
+        Group,
+        Uid,
+        Gid,
+        Inode,
+        Links,
+        Type,
+        LinkTarget,
+        AccessTime,
+        ChangeTime,
+        ModificationTime,
+        BirthTime
+    }
+
+    // the name of every field, as used by --columns, and its --printf directive
+    const FIELDS: [(&str, char, Field); 22] = [
+        ("path", 'p', Field::Path),
+        ("name", 'f', Field::Name),
+        ("dir", 'h', Field::Directory),
+        ("relpath", 'P', Field::RelativePath),
+        ("depth", 'd', Field::Depth),
+        ("size", 's', Field::Size),
+        ("blocks", 'b', Field::Blocks),
+        ("kblocks", 'k', Field::KiloBlocks),
+        ("perm", 'm', Field::Permissions),
+        ("mode", 'M', Field::Mode),
+        ("owner", 'u', Field::Owner),
+        ("group", 'g', Field::Group),
+        ("uid", 'U', Field::Uid),
+        ("gid", 'G', Field::Gid),
+        ("inode", 'i', Field::Inode),
+        ("links", 'n', Field::Links),
+        ("type", 'y', Field::Type),
+        ("target", 'l', Field::LinkTarget),
+        ("atime", 'a', Field::AccessTime),
+        ("ctime", 'c', Field::ChangeTime),
+        ("mtime", 't', Field::ModificationTime),
+        ("btime", 'w', Field::BirthTime)
+    ];
+
+    impl Field {
+        pub fn from_directive(directive: char) -> Option<Field> {
+            FIELDS.iter().find(|(_, field_directive, _)| *field_directive == directive).map(|(_, _, field)| *field)
+        }
+
+        /// Parses a column name such as `size`. The `--printf` directive letter is accepted as well.
+        pub fn from_name(name: &str) -> Option<Field> {
+            let mut chars = name.chars();
+            if let (Some(directive), None) = (chars.next(), chars.next()) {
+                return Field::from_directive(directive);
+            }
+            FIELDS.iter().find(|(field_name, _, _)| *field_name == name).map(|(_, _, field)| *field)
+        }
+
+        pub fn name(&self) -> &'static str {
+            FIELDS.iter().find(|(_, _, field)| field == self).map(|(name, _, _)| *name).unwrap()
+        }
+
+        /// The `statx` fields that the field is made from, such as `STATX_SIZE`.
+        #[cfg(target_os = "linux")]
+        pub fn stat_mask(&self) -> u32 {
+            match self {
+                Field::Path | Field::Name | Field::Directory | Field::RelativePath | Field::Depth => 0,
+                Field::Size => libc::STATX_SIZE,
+                Field::Blocks | Field::KiloBlocks => libc::STATX_BLOCKS,
+                Field::Permissions | Field::Mode => libc::STATX_MODE,
+                Field::Owner | Field::Uid => libc::STATX_UID,
+                Field::Group | Field::Gid => libc::STATX_GID,
+                Field::Inode => libc::STATX_INO,
+                Field::Links => libc::STATX_NLINK,
+                // whether there is a link target depends on the type
+                Field::Type | Field::LinkTarget => libc::STATX_TYPE,
+                Field::AccessTime => libc::STATX_ATIME,
+                Field::ChangeTime => libc::STATX_CTIME,
+                Field::ModificationTime => libc::STATX_MTIME,
+                Field::BirthTime => libc::STATX_BTIME
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        pub fn stat_mask(&self) -> u32 {
+            0
+        }
+
+        pub fn render(&self, entry: &Entry) -> String {
+            match self {
+                Field::Path => entry.path.to_string_lossy().into_owned(),
+                Field::Name => entry.name().to_string_lossy().into_owned(),
+                Field::Directory => match entry.path.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy().into_owned(),
+                    _ => ".".to_string()
+                },
+                Field::RelativePath => entry.relative_path().to_string_lossy().into_owned(),
+                Field::Depth => entry.depth.to_string(),
+                Field::Size => entry.stat().size.to_string(),
+                Field::Blocks => entry.stat().blocks.to_string(),
+                Field::KiloBlocks => entry.stat().blocks.div_ceil(2).to_string(),
+                Field::Permissions => format!("{:o}", entry.stat().mode & 0o7777),
+                Field::Mode => mode_string(entry.stat().mode),
+                Field::Owner => user_name(entry.stat().uid),
+                Field::Group => group_name(entry.stat().gid),
+                Field::Uid => entry.stat().uid.to_string(),
+                Field::Gid => entry.stat().gid.to_string(),
+                Field::Inode => entry.stat().ino.to_string(),
+                Field::Links => entry.stat().nlink.to_string(),
+                Field::Type => type_char(entry.stat().mode).to_string(),
+                Field::LinkTarget => entry.link_target().map(|target| target.to_string_lossy().into_owned()).unwrap_or_default(),
+                // empty when the file system does not record birth times
+                Field::AccessTime | Field::ChangeTime | Field::ModificationTime | Field::BirthTime => self.seconds(entry)
+                    .map(|seconds| format_time(seconds, "%a %b %e %H:%M:%S %Y"))
+                    .unwrap_or_default()
+            }
+        }
+
+        /// Like `render`, but the fields that hold a path are given byte for byte, even when the
+        /// path is not UTF-8.
+        pub fn render_bytes(&self, entry: &Entry) -> Vec<u8> {
+            match self {
+                Field::Path => entry.path.as_os_str().as_bytes().to_vec(),
+                Field::Name => entry.name().as_bytes().to_vec(),
+                Field::Directory => match entry.path.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent.as_os_str().as_bytes().to_vec(),
+                    _ => b".".to_vec()
+                },
+                Field::RelativePath => entry.relative_path().as_os_str().as_bytes().to_vec(),
+                Field::LinkTarget => entry.link_target().map(|target| target.as_os_str().as_bytes().to_vec()).unwrap_or_default(),
+                _ => self.render(entry).into_bytes()
+            }
+        }
+
+        /// The time held by a time field, in seconds since the epoch.
+        pub fn seconds(&self, entry: &Entry) -> Option<i64> {
+            match self {
+                Field::AccessTime => Some(entry.stat().atime),
+                Field::ChangeTime => Some(entry.stat().ctime),
+                Field::ModificationTime => Some(entry.stat().mtime),
+                Field::BirthTime => entry.stat().btime,
+                _ => None
+            }
+        }
+    }
+
+    fn type_char(mode: u32) -> char {
+        match mode & libc::S_IFMT {
+            libc::S_IFDIR => 'd',
+            libc::S_IFLNK => 'l',
+            libc::S_IFCHR => 'c',
+            libc::S_IFBLK => 'b',
+            libc::S_IFIFO => 'p',
+            libc::S_IFSOCK => 's',
+            _ => 'f'
+        }
+    }
+}
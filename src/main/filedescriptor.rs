@@ -1,10 +1,24 @@
 pub use self::filedescriptor::FileDescriptor;
 pub mod filedescriptor {
     use crate::main::*;
+    use std::os::fd::RawFd;
     #[derive(Clone, Debug, PartialEq, Copy)]
     pub enum FileDescriptor {
-        StdIn = 0,
-        StdOut = 1,
-        StdErr = 2
+        StdIn,
+        StdOut,
+        StdErr,
+        // a file opened by `OutputFiles`, which owns it
+        File(RawFd)
+    }
+
+    impl FileDescriptor {
+        pub fn as_raw_fd(&self) -> RawFd {
+            match self {
+                FileDescriptor::StdIn => 0,
+                FileDescriptor::StdOut => 1,
+                FileDescriptor::StdErr => 2,
+                FileDescriptor::File(fd) => *fd
+            }
+        }
     }
 }
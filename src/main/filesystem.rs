@@ -0,0 +1,269 @@
+pub use self::filesystem::{FileSystem, MemoryFileSystem, Os};
+pub mod filesystem {
+    use std::collections::{BTreeMap, BTreeSet, VecDeque};
+    use std::ffi::OsString;
+    use std::fmt::Debug;
+    use std::fs::{self, File};
+    use std::io::{self, Cursor, Read};
+    use std::os::unix::fs::DirEntryExt;
+    use std::path::{Component, Path, PathBuf};
+    use std::sync::Arc;
+
+    use crate::main::directory::{Directory, DirectoryEntry};
+    use crate::main::entry::Stat;
+    use crate::main::filetype::FileType;
+
+    // as many symlinks as Linux follows when it resolves a path
+    const MAX_SYMLINKS: usize = 40;
+
+    /// Where a search reads files from. The searcher opens the directories it starts from with
+    /// `open_directory`, and everything below them is read through the `Directory` that gives.
+    pub trait FileSystem: Debug + Send + Sync {
+        /// Opens a directory to search. Unless `follow_symlink` is set, this fails if the last
+        /// component is a symlink. A file system that is only read by path gives
+        /// `Directory::open_in(self, ..)`, which reads it through the other methods.
+        fn open_directory(self: Arc<Self>, path: &Path, follow_symlink: bool) -> io::Result<Directory>;
+
+        /// The entries of a directory, other than `.` and `..`.
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<DirectoryEntry>>;
+
+        /// The metadata of a file, or of the file a symlink points to when `follow_symlink` is set.
+        fn metadata(&self, path: &Path, follow_symlink: bool) -> io::Result<Stat>;
+
+        fn read_link(&self, path: &Path) -> io::Result<PathBuf>;
+
+        /// Opens a file to read its contents.
+        fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+
+        /// Removes a file, or a directory if it is empty. Most file systems can not be changed.
+        fn remove(&self, _path: &Path, _is_dir: bool) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::Unsupported, "files can not be removed from this file system"))
+        }
+    }
+
+    /// The file system of the OS. Its directories are opened as descriptors, which the directories
+    /// in them are opened relative to and read with getdents and statx, so that a search is not
+    /// limited by how long a path can be. The other methods read it by path.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Os;
+
+    impl FileSystem for Os {
+        fn open_directory(self: Arc<Self>, path: &Path, follow_symlink: bool) -> io::Result<Directory> {
+            Directory::open_path(path, follow_symlink)
+        }
+
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<DirectoryEntry>> {
+            fs::read_dir(path)?.map(|entry| {
+                let entry = entry?;
+                Ok(DirectoryEntry {
+                    name: entry.file_name(),
+                    ino: entry.ino(),
+                    // taken from the directory entry where the file system gives it, rather than stat'ing every entry
+                    file_type: entry.file_type().ok().map(FileType::from_std)
+                })
+            }).collect()
+        }
+
+        fn metadata(&self, path: &Path, follow_symlink: bool) -> io::Result<Stat> {
+            let metadata = match follow_symlink {
+                true => fs::metadata(path)?,
+                false => fs::symlink_metadata(path)?
+            };
+            Ok(Stat::from(&metadata))
+        }
+
+        fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+            fs::read_link(path)
+        }
+
+        fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+            Ok(Box::new(File::open(path)?))
+        }
+
+        fn remove(&self, path: &Path, is_dir: bool) -> io::Result<()> {
+            match is_dir {
+                true => fs::remove_dir(path),
+                false => fs::remove_file(path)
+            }
+        }
+    }
+
+    /// A file system that only exists in memory, which searches can run on without touching the
+    /// disk, e.g. in tests. Paths that are not absolute are taken to be relative to `/`.
+    #[derive(Debug, Clone)]
+    pub struct MemoryFileSystem {
+        nodes: BTreeMap<PathBuf, Node>
+    }
+
+    #[derive(Debug, Clone)]
+    struct Node {
+        ino: u64,
+        kind: Kind
+    }
+
+    #[derive(Debug, Clone)]
+    enum Kind {
+        // the names of the entries in the directory
+        Directory(BTreeSet<OsString>),
+        File(Vec<u8>),
+        Symlink(PathBuf)
+    }
+
+    impl Default for MemoryFileSystem {
+        fn default() -> MemoryFileSystem {
+            MemoryFileSystem::new()
+        }
+    }
+
+    impl MemoryFileSystem {
+        /// An empty file system, with only `/` in it.
+        pub fn new() -> MemoryFileSystem {
+            let root = Node { ino: 1, kind: Kind::Directory(BTreeSet::new()) };
+            MemoryFileSystem { nodes: BTreeMap::from([(PathBuf::from("/"), root)]) }
+        }
+
+        /// Adds a file, and the directories it is in if they are not there yet.
+        pub fn with_file<P: AsRef<Path>, C: Into<Vec<u8>>>(mut self, path: P, contents: C) -> MemoryFileSystem {
+            self.insert(&normalize(path.as_ref()), Kind::File(contents.into()));
+            self
+        }
+
+        pub fn with_directory<P: AsRef<Path>>(mut self, path: P) -> MemoryFileSystem {
+            self.insert(&normalize(path.as_ref()), Kind::Directory(BTreeSet::new()));
+            self
+        }
+
+        /// Adds a symlink to `target`, which is resolved relative to the directory the symlink is
+        /// in unless it is absolute. It does not have to exist.
+        pub fn with_symlink<P: AsRef<Path>, T: AsRef<Path>>(mut self, path: P, target: T) -> MemoryFileSystem {
+            self.insert(&normalize(path.as_ref()), Kind::Symlink(target.as_ref().to_path_buf()));
+            self
+        }
+
+        fn insert(&mut self, path: &Path, kind: Kind) {
+            let Some(parent) = path.parent() else {
+                return;
+            };
+            if !matches!(self.nodes.get(parent), Some(Node { kind: Kind::Directory(_), .. })) {
+                self.insert(parent, Kind::Directory(BTreeSet::new()));
+            }
+            if let Some(Node { kind: Kind::Directory(children), .. }) = self.nodes.get_mut(parent) {
+                children.insert(path.file_name().unwrap().to_os_string());
+            }
+            match (self.nodes.get_mut(path), kind) {
+                // the entries of a directory that is already there are kept
+                (Some(Node { kind: Kind::Directory(_), .. }), Kind::Directory(_)) => {}
+                (Some(node), kind) => node.kind = kind,
+                (None, kind) => {
+                    let ino = self.nodes.len() as u64 + 1;
+                    self.nodes.insert(path.to_path_buf(), Node { ino, kind });
+                }
+            }
+        }
+
+        // Finds the node at `path`, following every symlink on the way to it, and the last one too
+        // when `follow_symlink` is set.
+        fn lookup(&self, path: &Path, follow_symlink: bool) -> io::Result<&Node> {
+            let mut pending: VecDeque<PathBuf> = path.components().map(|component| PathBuf::from(component.as_os_str())).collect();
+            let mut resolved = PathBuf::from("/");
+            let mut symlinks = 0;
+            while let Some(component) = pending.pop_front() {
+                match component.components().next() {
+                    Some(Component::RootDir) => resolved = PathBuf::from("/"),
+                    Some(Component::ParentDir) => _ = resolved.pop(),
+                    Some(Component::Normal(name)) => {
+                        let candidate = resolved.join(name);
+                        let node = self.nodes.get(&candidate).ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+                        match &node.kind {
+                            Kind::Symlink(target) if follow_symlink || !pending.is_empty() => {
+                                symlinks += 1;
+                                if symlinks > MAX_SYMLINKS {
+                                    return Err(io::Error::from_raw_os_error(libc::ELOOP));
+                                }
+                                // the target is resolved from the directory that the symlink is in
+                                for component in target.components().rev() {
+                                    pending.push_front(PathBuf::from(component.as_os_str()));
+                                }
+                            }
+                            Kind::Directory(_) => resolved = candidate,
+                            _ if pending.is_empty() => resolved = candidate,
+                            _ => return Err(io::Error::from_raw_os_error(libc::ENOTDIR))
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(&self.nodes[&resolved])
+        }
+    }
+
+    impl FileSystem for MemoryFileSystem {
+        fn open_directory(self: Arc<Self>, path: &Path, follow_symlink: bool) -> io::Result<Directory> {
+            Directory::open_in(self, path, follow_symlink)
+        }
+
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<DirectoryEntry>> {
+            let Kind::Directory(children) = &self.lookup(path, true)?.kind else {
+                return Err(io::Error::from_raw_os_error(libc::ENOTDIR));
+            };
+            let directory = normalize(path);
+            children.iter().map(|name| {
+                let node = self.lookup(&directory.join(name), false)?;
+                Ok(DirectoryEntry { name: name.clone(), ino: node.ino, file_type: Some(node.file_type()) })
+            }).collect()
+        }
+
+        fn metadata(&self, path: &Path, follow_symlink: bool) -> io::Result<Stat> {
+            let node = self.lookup(path, follow_symlink)?;
+            let (permissions, size) = match &node.kind {
+                Kind::Directory(_) => (0o755, 0),
+                Kind::File(contents) => (0o644, contents.len() as u64),
+                Kind::Symlink(target) => (0o777, target.as_os_str().len() as u64)
+            };
+            // the type bits that POSIX gives S_IFDIR, S_IFLNK and S_IFREG
+            let type_bits = match node.file_type() {
+                FileType::Directory => 0o040000,
+                FileType::Symlink => 0o120000,
+                _ => 0o100000
+            };
+            Ok(Stat { dev: 1, ino: node.ino, mode: type_bits | permissions, nlink: 1, size, ..Default::default() })
+        }
+
+        fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+            match &self.lookup(path, false)?.kind {
+                Kind::Symlink(target) => Ok(target.clone()),
+                _ => Err(io::Error::from_raw_os_error(libc::EINVAL))
+            }
+        }
+
+        fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+            match &self.lookup(path, true)?.kind {
+                Kind::File(contents) => Ok(Box::new(Cursor::new(contents.clone()))),
+                _ => Err(io::Error::from_raw_os_error(libc::EISDIR))
+            }
+        }
+    }
+
+    impl Node {
+        fn file_type(&self) -> FileType {
+            match self.kind {
+                Kind::Directory(_) => FileType::Directory,
+                Kind::File(_) => FileType::File,
+                Kind::Symlink(_) => FileType::Symlink
+            }
+        }
+    }
+
+    // Makes a path absolute and takes out `.` and `..`, without looking at symlinks.
+    fn normalize(path: &Path) -> PathBuf {
+        let mut normalized = PathBuf::from("/");
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => normalized.push(name),
+                Component::ParentDir => _ = normalized.pop(),
+                _ => {}
+            }
+        }
+        normalized
+    }
+}
@@ -0,0 +1,86 @@
+pub use self::filetype::FileType;
+pub mod filetype {
+    use std::fs;
+    use std::os::unix::fs::FileTypeExt;
+
+    /// The type of a file, as told by a directory entry or by a stat call.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum FileType {
+        BlockDevice,
+        CharDevice,
+        Directory,
+        Fifo,
+        Symlink,
+        File,
+        Socket
+    }
+
+    impl FileType {
+        /// Returns `None` for `DT_UNKNOWN`, which some file systems give for every entry.
+        pub fn from_d_type(d_type: u8) -> Option<FileType> {
+            match d_type {
+                libc::DT_BLK => Some(FileType::BlockDevice),
+                libc::DT_CHR => Some(FileType::CharDevice),
+                libc::DT_DIR => Some(FileType::Directory),
+                libc::DT_FIFO => Some(FileType::Fifo),
+                libc::DT_LNK => Some(FileType::Symlink),
+                libc::DT_REG => Some(FileType::File),
+                libc::DT_SOCK => Some(FileType::Socket),
+                _ => None
+            }
+        }
+
+        pub fn from_mode(mode: u32) -> FileType {
+            match mode & libc::S_IFMT {
+                libc::S_IFBLK => FileType::BlockDevice,
+                libc::S_IFCHR => FileType::CharDevice,
+                libc::S_IFDIR => FileType::Directory,
+                libc::S_IFIFO => FileType::Fifo,
+                libc::S_IFLNK => FileType::Symlink,
+                libc::S_IFSOCK => FileType::Socket,
+                _ => FileType::File
+            }
+        }
+
+        /// The type that the standard library gives, e.g. for an entry of `fs::read_dir`.
+        pub fn from_std(file_type: fs::FileType) -> FileType {
+            match file_type {
+                file_type if file_type.is_block_device() => FileType::BlockDevice,
+                file_type if file_type.is_char_device() => FileType::CharDevice,
+                file_type if file_type.is_dir() => FileType::Directory,
+                file_type if file_type.is_fifo() => FileType::Fifo,
+                file_type if file_type.is_symlink() => FileType::Symlink,
+                file_type if file_type.is_socket() => FileType::Socket,
+                _ => FileType::File
+            }
+        }
+
+        pub fn is_block_device(&self) -> bool {
+            *self == FileType::BlockDevice
+        }
+
+        pub fn is_char_device(&self) -> bool {
+            *self == FileType::CharDevice
+        }
+
+        pub fn is_dir(&self) -> bool {
+            *self == FileType::Directory
+        }
+
+        pub fn is_fifo(&self) -> bool {
+            *self == FileType::Fifo
+        }
+
+        pub fn is_symlink(&self) -> bool {
+            *self == FileType::Symlink
+        }
+
+        pub fn is_file(&self) -> bool {
+            *self == FileType::File
+        }
+
+        pub fn is_socket(&self) -> bool {
+            *self == FileType::Socket
+        }
+    }
+}
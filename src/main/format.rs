@@ -0,0 +1,115 @@
+pub use self::format::{format_time, group_name, long_listing, mode_string, user_name};
+pub mod format {
+    use std::collections::HashMap;
+    use std::ffi::CStr;
+    use std::sync::{Mutex, OnceLock};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use crate::main::entry::Entry;
+
+    const SIX_MONTHS_IN_SECONDS: i64 = 31556952 / 2;
+
+    /// Formats an entry the way `find -ls` does, e.g.
+    /// `  1220629      4 -rw-r--r--   1 root     root         1221 May 20 16:27 ./file`
+    pub fn long_listing(entry: &Entry) -> String {
+        let stat = entry.stat();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs() as i64).unwrap_or(0);
+        let time_format = match stat.mtime > now - SIX_MONTHS_IN_SECONDS && stat.mtime <= now {
+            true => "%b %e %H:%M",
+            false => "%b %e  %Y"
+        };
+        let mut line = format!("{:>9} {:>6} {} {:>3} {:<8} {:<8} {:>8} {} {}",
+            stat.ino,
+            stat.blocks.div_ceil(2),
+            mode_string(stat.mode),
+            stat.nlink,
+            user_name(stat.uid),
+            group_name(stat.gid),
+            stat.size,
+            format_time(stat.mtime, time_format),
+            entry.path.to_string_lossy());
+        if let Some(link_target) = entry.link_target() {
+            line.push_str(&format!(" -> {}", link_target.to_string_lossy()));
+        }
+        line
+    }
+
+    /// Formats a mode the way `ls -l` does, e.g. `drwxr-xr-x`.
+    pub fn mode_string(mode: u32) -> String {
+        let file_type = match mode & libc::S_IFMT {
+            libc::S_IFDIR => 'd',
+            libc::S_IFLNK => 'l',
+            libc::S_IFCHR => 'c',
+            libc::S_IFBLK => 'b',
+            libc::S_IFIFO => 'p',
+            libc::S_IFSOCK => 's',
+            _ => '-'
+        };
+        let permission = |bit: u32, c: char| if mode & bit != 0 { c } else { '-' };
+        // the execute position doubles as the setuid, setgid and sticky bits
+        let special = |execute: u32, special: u32, set: char, unset: char| match (mode & execute != 0, mode & special != 0) {
+            (true, true) => set,
+            (false, true) => unset,
+            (true, false) => 'x',
+            (false, false) => '-'
+        };
+        [
+            file_type,
+            permission(libc::S_IRUSR, 'r'),
+            permission(libc::S_IWUSR, 'w'),
+            special(libc::S_IXUSR, libc::S_ISUID, 's', 'S'),
+            permission(libc::S_IRGRP, 'r'),
+            permission(libc::S_IWGRP, 'w'),
+            special(libc::S_IXGRP, libc::S_ISGID, 's', 'S'),
+            permission(libc::S_IROTH, 'r'),
+            permission(libc::S_IWOTH, 'w'),
+            special(libc::S_IXOTH, libc::S_ISVTX, 't', 'T')
+        ].iter().collect()
+    }
+
+    /// Looks up the name of a user, falling back to the uid when the user does not exist.
+    pub fn user_name(uid: u32) -> String {
+        static USERS: OnceLock<Mutex<HashMap<u32, String>>> = OnceLock::new();
+        USERS.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap().entry(uid).or_insert_with(|| {
+            let mut buffer = vec![0 as libc::c_char; 4096];
+            let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+            let mut result: *mut libc::passwd = std::ptr::null_mut();
+            let status = unsafe { libc::getpwuid_r(uid, &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+            match status == 0 && !result.is_null() {
+                true => unsafe { CStr::from_ptr(passwd.pw_name) }.to_string_lossy().into_owned(),
+                false => uid.to_string()
+            }
+        }).clone()
+    }
+
+    /// Looks up the name of a group, falling back to the gid when the group does not exist.
+    pub fn group_name(gid: u32) -> String {
+        static GROUPS: OnceLock<Mutex<HashMap<u32, String>>> = OnceLock::new();
+        GROUPS.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap().entry(gid).or_insert_with(|| {
+            let mut buffer = vec![0 as libc::c_char; 4096];
+            let mut group: libc::group = unsafe { std::mem::zeroed() };
+            let mut result: *mut libc::group = std::ptr::null_mut();
+            let status = unsafe { libc::getgrgid_r(gid, &mut group, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+            match status == 0 && !result.is_null() {
+                true => unsafe { CStr::from_ptr(group.gr_name) }.to_string_lossy().into_owned(),
+                false => gid.to_string()
+            }
+        }).clone()
+    }
+
+    /// Formats seconds since the epoch in local time using a `strftime` format string.
+    pub fn format_time(seconds: i64, format: &str) -> String {
+        let Ok(format) = std::ffi::CString::new(format) else {
+            return seconds.to_string();
+        };
+        let time = seconds as libc::time_t;
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+            return seconds.to_string();
+        }
+        let mut buffer = [0 as libc::c_char; 256];
+        let length = unsafe { libc::strftime(buffer.as_mut_ptr(), buffer.len(), format.as_ptr(), &tm) };
+        let bytes: Vec<u8> = buffer[..length].iter().map(|&c| c as u8).collect();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
@@ -0,0 +1,113 @@
+pub use self::jsonlogger::{entry_to_json, json_bytes, json_string, JsonLogger};
+pub mod jsonlogger {
+    use std::os::unix::ffi::OsStrExt;
+
+    use crate::main::entry::Entry;
+    use crate::main::filedescriptor::FileDescriptor;
+    use crate::main::line::Line;
+    use crate::main::logger::Logger;
+    use crate::main::message::Message;
+    use crate::main::standardlogger::{write_to_file_descriptor, StandardLogger};
+
+    /// Writes every match as a JSON object on its own line, and every error as an object with
+    /// `"type": "error"`. Everything else, such as the output of `--printf`, is written unchanged.
+    #[derive(Debug, Default)]
+    pub struct JsonLogger { }
+
+    impl JsonLogger {
+        pub fn new() -> JsonLogger {
+            JsonLogger {
+            }
+        }
+    }
+
+    impl Logger for JsonLogger {
+        fn log(&mut self, line: Line) {
+            match (&line.message, line.file_descriptor) {
+                (Message::Entry(entry), file_descriptor) => {
+                    write_to_file_descriptor(file_descriptor, format!("{}\n", entry_to_json(entry)).as_bytes());
+                }
+                (message, Some(FileDescriptor::StdErr)) => {
+                    let error = format!("{{\"type\":\"error\",\"message\":{}}}\n", json_string(message.get_contained_message().as_bytes()));
+                    write_to_file_descriptor(Some(FileDescriptor::StdOut), error.as_bytes());
+                }
+                _ => StandardLogger::new().log(line)
+            }
+        }
+
+        fn log_as_tree(&mut self, dir_entries: Vec<(String, bool)>, preceding_str: Option<String>) -> Vec<String> {
+            StandardLogger::new().log_as_tree(dir_entries, preceding_str)
+        }
+    }
+
+    pub fn entry_to_json(entry: &Entry) -> String {
+        let stat = entry.stat();
+        let file_type = match stat.mode & libc::S_IFMT {
+            libc::S_IFDIR => "directory",
+            libc::S_IFLNK => "symlink",
+            libc::S_IFCHR => "char_device",
+            libc::S_IFBLK => "block_device",
+            libc::S_IFIFO => "fifo",
+            libc::S_IFSOCK => "socket",
+            _ => "file"
+        };
+        let target = match entry.link_target() {
+            Some(target) => json_string(target.as_os_str().as_bytes()),
+            None => "null".to_string()
+        };
+        // the exact bytes of a path that is not UTF-8, since its string has U+FFFD in their place
+        let path_bytes = match entry.path.to_str() {
+            Some(_) => String::new(),
+            None => format!(",\"path_bytes\":{}", json_bytes(entry.path.as_os_str().as_bytes()))
+        };
+        let target_bytes = match entry.link_target() {
+            Some(target) if target.to_str().is_none() => format!(",\"target_bytes\":{}", json_bytes(target.as_os_str().as_bytes())),
+            _ => String::new()
+        };
+        format!("{{\"type\":\"match\",\"path\":{}{},\"name\":{},\"depth\":{},\"file_type\":\"{}\",\"size\":{},\"mode\":{},\"uid\":{},\"gid\":{},\"mtime\":{},\"target\":{}{}}}",
+            json_string(entry.path.as_os_str().as_bytes()),
+            path_bytes,
+            json_string(entry.name().as_bytes()),
+            entry.depth,
+            file_type,
+            stat.size,
+            stat.mode & 0o7777,
+            stat.uid,
+            stat.gid,
+            stat.mtime,
+            target,
+            target_bytes)
+    }
+
+    /// Quotes bytes as a JSON string. File names do not have to be UTF-8, but JSON strings do, so
+    /// bytes that are not valid UTF-8 are replaced with U+FFFD, as `String::from_utf8_lossy` does.
+    /// Use `json_bytes` to keep the original bytes.
+    pub fn json_string(bytes: &[u8]) -> String {
+        let mut result = String::with_capacity(bytes.len() + 2);
+        result.push('"');
+        for chunk in bytes.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                match c {
+                    '"' => result.push_str("\\\""),
+                    '\\' => result.push_str("\\\\"),
+                    '\n' => result.push_str("\\n"),
+                    '\r' => result.push_str("\\r"),
+                    '\t' => result.push_str("\\t"),
+                    c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => result.push(c)
+                }
+            }
+            if !chunk.invalid().is_empty() {
+                result.push(char::REPLACEMENT_CHARACTER);
+            }
+        }
+        result.push('"');
+        result
+    }
+
+    /// Writes bytes as a JSON array of numbers, e.g. `[97,255]`.
+    pub fn json_bytes(bytes: &[u8]) -> String {
+        let numbers: Vec<String> = bytes.iter().map(|byte| byte.to_string()).collect();
+        format!("[{}]", numbers.join(","))
+    }
+}
@@ -7,4 +7,14 @@ pub mod logger {
         fn log(&mut self, line: Line);
         fn log_as_tree(&mut self, dir_entries: Vec<(String, bool)>, preceding_str: Option<String>) -> Vec<String>;
     }
+
+    impl<L: Logger + ?Sized> Logger for Box<L> {
+        fn log(&mut self, line: Line) {
+            (**self).log(line)
+        }
+
+        fn log_as_tree(&mut self, dir_entries: Vec<(String, bool)>, preceding_str: Option<String>) -> Vec<String> {
+            (**self).log_as_tree(dir_entries, preceding_str)
+        }
+    }
 }
@@ -1,20 +1,46 @@
 pub use self::message::Message;
 pub mod message {
     use crate::main::*;
+    use std::borrow::Cow;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+    use crate::main::entry::Entry;
+    use crate::main::errorcounts::ErrorCategory;
     #[derive(Clone, Debug, PartialEq)]
     pub enum Message {
         Standard(String),
-        Tree(String)
+        Tree(String),
+        // a path that is written byte for byte, even if it is not UTF-8
+        Path(PathBuf),
+        // written exactly as it is, without a newline at the end
+        Raw(Vec<u8>),
+        // a match that loggers can describe in more detail than its path
+        Entry(Box<Entry>),
+        // an error that the search ran into, which loggers write like any other message
+        Error(ErrorCategory, String)
     }
 
     impl Message {
-        pub fn get_contained_message(&self) -> &String {
+        pub fn get_contained_message(&self) -> Cow<'_, str> {
             match self {
-                Self::Standard(x) | Self::Tree(x) => x, 
+                Self::Standard(x) | Self::Tree(x) | Self::Error(_, x) => Cow::Borrowed(x), 
+                Self::Path(path) => path.to_string_lossy(),
+                Self::Raw(bytes) => String::from_utf8_lossy(bytes),
+                Self::Entry(entry) => entry.path.to_string_lossy()
             }
             //todo fix so that this works so that we don't
             //have to update this method every time a new type of message is added to the message
             //enum.
         }
+
+        /// The message as it is written, which for a path are the bytes of its name.
+        pub fn bytes(&self) -> Cow<'_, [u8]> {
+            match self {
+                Self::Path(path) => Cow::Borrowed(path.as_os_str().as_bytes()),
+                Self::Raw(bytes) => Cow::Borrowed(bytes),
+                Self::Entry(entry) => Cow::Borrowed(entry.path.as_os_str().as_bytes()),
+                Self::Standard(x) | Self::Tree(x) | Self::Error(_, x) => Cow::Borrowed(x.as_bytes())
+            }
+        }
     }
 }
@@ -1,8 +0,0 @@
-pub use self::multithreadmessage::MultiThreadMessage;
-pub mod multithreadmessage {
-    use crate::main::job::Job;
-    pub enum MultiThreadMessage {
-        NewJob(Job),
-        Terminate
-    }
-}
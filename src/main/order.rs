@@ -0,0 +1,85 @@
+pub use self::order::{Order, SortBy, SortKey};
+pub mod order {
+    use std::cmp::Ordering;
+    use std::ffi::OsString;
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::{Path, PathBuf};
+
+    use crate::main::entry::Entry;
+    use crate::main::field::Field;
+
+    /// The order that the output thread writes matches in.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Order {
+        // whatever order the threads find them in
+        Traversal,
+        // the order a sequential depth-first search would have found them in
+        Stable,
+        // every match is held until the search has finished
+        Sorted(SortBy, bool /* reverse */)
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum SortBy {
+        Name,
+        Path,
+        Size,
+        ModificationTime
+    }
+
+    /// What a match is sorted by. Matches that compare equal are sorted by their path.
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum SortKey {
+        Name(OsString, PathBuf),
+        Path(PathBuf),
+        Number(i64, PathBuf)
+    }
+
+    impl SortBy {
+        pub fn from_name(name: &str) -> Option<SortBy> {
+            match name {
+                "name" => Some(SortBy::Name),
+                "path" => Some(SortBy::Path),
+                "size" => Some(SortBy::Size),
+                "mtime" => Some(SortBy::ModificationTime),
+                _ => None
+            }
+        }
+
+        /// The `statx` fields that the key is made from.
+        pub fn stat_mask(&self) -> u32 {
+            match self {
+                SortBy::Name | SortBy::Path => 0,
+                SortBy::Size => Field::Size.stat_mask(),
+                SortBy::ModificationTime => Field::ModificationTime.stat_mask()
+            }
+        }
+
+        /// Stats the path for sizes and times when there is no entry to fetch them from.
+        /// Files that can no longer be stat'ed sort as if they were empty and from the epoch.
+        pub fn key(&self, path: &Path, entry: Option<&Entry>) -> SortKey {
+            let number = |field: fn(&fs::Metadata) -> i64, entry_field: fn(&Entry) -> i64| {
+                match entry {
+                    Some(entry) => entry_field(entry),
+                    None => fs::symlink_metadata(path).map(|metadata| field(&metadata)).unwrap_or(0)
+                }
+            };
+            match self {
+                SortBy::Name => SortKey::Name(path.file_name().unwrap_or(path.as_os_str()).to_os_string(), path.to_path_buf()),
+                SortBy::Path => SortKey::Path(path.to_path_buf()),
+                SortBy::Size => SortKey::Number(number(|metadata| metadata.size() as i64, |entry| entry.stat().size as i64), path.to_path_buf()),
+                SortBy::ModificationTime => SortKey::Number(number(|metadata| metadata.mtime(), |entry| entry.stat().mtime), path.to_path_buf())
+            }
+        }
+    }
+
+    impl Order {
+        pub fn compare(&self, a: &SortKey, b: &SortKey) -> Ordering {
+            match self {
+                Order::Sorted(_, true) => b.cmp(a),
+                _ => a.cmp(b)
+            }
+        }
+    }
+}
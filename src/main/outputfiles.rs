@@ -0,0 +1,50 @@
+pub use self::outputfiles::OutputFiles;
+pub mod outputfiles {
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::{Path, PathBuf};
+
+    use crate::main::filedescriptor::FileDescriptor;
+
+    /// The files named by actions such as `--fprint`. Every file is opened once, when the
+    /// expression is parsed, and actions that name the same file share it. The files are closed
+    /// when this is dropped, so it has to outlive every `Line` that refers to them.
+    #[derive(Debug, Default)]
+    pub struct OutputFiles {
+        files: Vec<(PathBuf, File)>
+    }
+
+    impl OutputFiles {
+        pub fn new() -> OutputFiles {
+            OutputFiles {
+                files: Vec::new()
+            }
+        }
+
+        pub fn open(&mut self, path: &Path) -> io::Result<FileDescriptor> {
+            if path == Path::new("/dev/stdout") {
+                return Ok(FileDescriptor::StdOut);
+            }
+            if path == Path::new("/dev/stderr") {
+                return Ok(FileDescriptor::StdErr);
+            }
+            if let Some((_, file)) = self.files.iter().find(|(opened_path, _)| opened_path == path) {
+                return Ok(FileDescriptor::File(file.as_raw_fd()));
+            }
+            let file = File::create(path)?;
+            // a different name for a file that is already open, e.g. through a hard link
+            let metadata = file.metadata()?;
+            for (_, opened_file) in &self.files {
+                let opened_metadata = opened_file.metadata()?;
+                if opened_metadata.dev() == metadata.dev() && opened_metadata.ino() == metadata.ino() {
+                    return Ok(FileDescriptor::File(opened_file.as_raw_fd()));
+                }
+            }
+            let file_descriptor = FileDescriptor::File(file.as_raw_fd());
+            self.files.push((path.to_path_buf(), file));
+            Ok(file_descriptor)
+        }
+    }
+}
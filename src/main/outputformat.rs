@@ -0,0 +1,11 @@
+pub use self::outputformat::OutputFormat;
+pub mod outputformat {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum OutputFormat {
+        Standard,
+        // one JSON object per line
+        Json,
+        Csv,
+        Tsv
+    }
+}
@@ -3,10 +3,36 @@ pub mod params {
     use crate::main::*;
     use crate::main::symlinksetting::SymLinkSetting;
     use crate::main::debugopts::DebugOpts;
+    use crate::main::action::Action;
+    use crate::main::filedescriptor::FileDescriptor;
+    use crate::main::outputformat::OutputFormat;
     #[derive(Debug, Clone)]
     pub struct Params {
         pub symlink_setting: SymLinkSetting,
         pub debug_opts: Option<DebugOpts>,
-        pub optimisation_level: Option<u8> 
+        pub optimisation_level: Option<u8>,
+        pub actions: Vec<Action>,
+        // process the contents of a directory before the directory itself
+        pub depth_first: bool,
+        // search the directories of each level before those of the next one
+        pub breadth_first: bool,
+        pub output_format: OutputFormat,
+        // entries that vanish between being read from their directory and being looked at do not fail the search
+        pub ignore_readdir_race: bool
+    }
+
+    impl Default for Params {
+        fn default() -> Params {
+            Params {
+                symlink_setting: SymLinkSetting::Never,
+                debug_opts: None,
+                optimisation_level: None,
+                actions: vec![Action::Print(FileDescriptor::StdOut)],
+                depth_first: false,
+                breadth_first: false,
+                output_format: OutputFormat::Standard,
+                ignore_readdir_race: false
+            }
+        }
     }
 }
@@ -0,0 +1,214 @@
+pub use self::predicate::{All, Any, Cost, False, Name, Not, PathRegex, Predicate, True, Types};
+pub mod predicate {
+    use std::fmt::Debug;
+    use std::os::unix::ffi::OsStrExt;
+    use std::sync::Arc;
+
+    use regex::bytes::Regex;
+
+    use crate::main::entry::{Entry, STAT_TYPE};
+
+    /// How expensive a predicate is to check, so that the cheaper ones can be checked first.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Cost {
+        // only looks at the path, or at the type that the directory gives with the name
+        Cheap,
+        // stats the file
+        Metadata,
+        // reads the file or runs something, e.g. a checksum
+        Expensive
+    }
+
+    /// A test that an entry either passes or fails. The built-in tests are predicates, and a
+    /// library can search with its own through `Test::Custom`, e.g. with `SearchBuilder::filter`,
+    /// or name it in an expression once it is in a `Registry`. Predicates are checked on every
+    /// thread of the search, so they have to be `Send` and `Sync`.
+    pub trait Predicate: Debug + Send + Sync {
+        fn matches(&self, entry: &Entry) -> bool;
+
+        /// Used by `All`, to check the cheaper predicates first, e.g. for the `--and`s of an expression.
+        fn cost(&self) -> Cost;
+
+        /// The `statx` fields that `matches` reads, on top of the type that every entry has.
+        /// Fields that are not asked for may not be filled in.
+        fn stat_mask(&self) -> u32;
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct Name(pub String);
+
+    impl Predicate for Name {
+        fn matches(&self, entry: &Entry) -> bool {
+            entry.name() == self.0.as_str()
+        }
+
+        fn cost(&self) -> Cost {
+            Cost::Cheap
+        }
+
+        fn stat_mask(&self) -> u32 {
+            0
+        }
+    }
+
+    /// Matches a file that is any one of the types, e.g. `fd` for files and directories.
+    #[derive(Debug, Clone)]
+    pub struct Types(pub String);
+
+    impl Predicate for Types {
+        fn matches(&self, entry: &Entry) -> bool {
+            let Ok(file_type) = entry.file_type() else {
+                return false;
+            };
+            self.0.chars().any(|letter| match letter {
+                'b' => file_type.is_block_device(),
+                'c' => file_type.is_char_device(),
+                'd' => file_type.is_dir(),
+                'f' => file_type.is_file(),
+                'p' => file_type.is_fifo(),
+                'l' => file_type.is_symlink(),
+                's' => file_type.is_socket(),
+                _ => false
+            })
+        }
+
+        // the directory usually gives the type, but not on every file system
+        fn cost(&self) -> Cost {
+            Cost::Metadata
+        }
+
+        fn stat_mask(&self) -> u32 {
+            STAT_TYPE
+        }
+    }
+
+    /// Matches the whole path of a file against a regular expression, as find does, so that e.g.
+    /// `.*\.txt` matches `./notes.txt` but `notes` does not. Paths that are not UTF-8 are matched
+    /// as they are.
+    #[derive(Debug, Clone)]
+    pub struct PathRegex(Regex);
+
+    impl PathRegex {
+        pub fn new(regex: &str) -> Result<PathRegex, String> {
+            match Regex::new(&format!("^(?:{})$", regex)) {
+                Ok(compiled) => Ok(PathRegex(compiled)),
+                Err(error) => Err(format!("invalid regular expression {}: {}", regex, error))
+            }
+        }
+    }
+
+    impl Predicate for PathRegex {
+        fn matches(&self, entry: &Entry) -> bool {
+            self.0.is_match(entry.path.as_os_str().as_bytes())
+        }
+
+        fn cost(&self) -> Cost {
+            Cost::Cheap
+        }
+
+        fn stat_mask(&self) -> u32 {
+            0
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct True;
+
+    impl Predicate for True {
+        fn matches(&self, _entry: &Entry) -> bool {
+            true
+        }
+
+        fn cost(&self) -> Cost {
+            Cost::Cheap
+        }
+
+        fn stat_mask(&self) -> u32 {
+            0
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct False;
+
+    impl Predicate for False {
+        fn matches(&self, _entry: &Entry) -> bool {
+            false
+        }
+
+        fn cost(&self) -> Cost {
+            Cost::Cheap
+        }
+
+        fn stat_mask(&self) -> u32 {
+            0
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct Not(pub Arc<dyn Predicate>);
+
+    impl Predicate for Not {
+        fn matches(&self, entry: &Entry) -> bool {
+            !self.0.matches(entry)
+        }
+
+        fn cost(&self) -> Cost {
+            self.0.cost()
+        }
+
+        fn stat_mask(&self) -> u32 {
+            self.0.stat_mask()
+        }
+    }
+
+    /// Matches an entry that every one of the predicates matches. They are checked from the
+    /// cheapest to the most expensive, and in the order they were given in when they cost the same.
+    #[derive(Debug, Clone)]
+    pub struct All(Vec<Arc<dyn Predicate>>);
+
+    impl All {
+        pub fn new(mut predicates: Vec<Arc<dyn Predicate>>) -> All {
+            predicates.sort_by_key(|predicate| predicate.cost());
+            All(predicates)
+        }
+
+        /// Checks the predicates in the order they were given in, whatever they cost.
+        pub fn in_order(predicates: Vec<Arc<dyn Predicate>>) -> All {
+            All(predicates)
+        }
+    }
+
+    impl Predicate for All {
+        fn matches(&self, entry: &Entry) -> bool {
+            self.0.iter().all(|predicate| predicate.matches(entry))
+        }
+
+        fn cost(&self) -> Cost {
+            self.0.iter().map(|predicate| predicate.cost()).max().unwrap_or(Cost::Cheap)
+        }
+
+        fn stat_mask(&self) -> u32 {
+            self.0.iter().fold(0, |mask, predicate| mask | predicate.stat_mask())
+        }
+    }
+
+    /// Matches an entry that any one of the predicates matches. They are checked in the order they
+    /// were given in, up to the first one that matches.
+    #[derive(Debug, Clone)]
+    pub struct Any(pub Vec<Arc<dyn Predicate>>);
+
+    impl Predicate for Any {
+        fn matches(&self, entry: &Entry) -> bool {
+            self.0.iter().any(|predicate| predicate.matches(entry))
+        }
+
+        fn cost(&self) -> Cost {
+            self.0.iter().map(|predicate| predicate.cost()).max().unwrap_or(Cost::Cheap)
+        }
+
+        fn stat_mask(&self) -> u32 {
+            self.0.iter().fold(0, |mask, predicate| mask | predicate.stat_mask())
+        }
+    }
+}
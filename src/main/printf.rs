@@ -0,0 +1,142 @@
+pub use self::printf::Printf;
+pub mod printf {
+    use crate::main::entry::Entry;
+    use crate::main::field::Field;
+    use crate::main::format::format_time;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Segment {
+        // bytes rather than a string, since an octal escape such as \377 stands for a single byte
+        Literal(Vec<u8>),
+        Field { field: Field, width: Option<usize>, left_align: bool },
+        // %A, %C and %T take a second character that selects a part of the time
+        Time { field: Field, format: char }
+    }
+
+    /// A parsed `--printf` format, e.g. `%p %s\n`.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Printf {
+        segments: Vec<Segment>
+    }
+
+    impl Printf {
+        pub fn parse(format: &str) -> Result<Printf, String> {
+            let mut segments = Vec::new();
+            let mut literal = Vec::new();
+            let mut chars = format.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => literal.extend(unescape(&mut chars)),
+                    '%' => {
+                        if chars.peek() == Some(&'%') {
+                            chars.next();
+                            literal.push(b'%');
+                            continue;
+                        }
+                        if !literal.is_empty() {
+                            segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                        }
+                        let left_align = chars.next_if_eq(&'-').is_some();
+                        let mut width = String::new();
+                        while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+                            width.push(digit);
+                        }
+                        let directive = chars.next().ok_or("--printf format ends with an incomplete % directive")?;
+                        let time_field = match directive {
+                            'A' => Some(Field::AccessTime),
+                            'C' => Some(Field::ChangeTime),
+                            'T' => Some(Field::ModificationTime),
+                            'B' => Some(Field::BirthTime),
+                            _ => None
+                        };
+                        let segment = match time_field {
+                            Some(field) => {
+                                let format = chars.next().ok_or(format!("--printf directive %{} expects a time format character", directive))?;
+                                Segment::Time { field, format }
+                            }
+                            None => Segment::Field {
+                                field: Field::from_directive(directive).ok_or(format!("unrecognised --printf directive %{}", directive))?,
+                                width: width.parse().ok(),
+                                left_align
+                            }
+                        };
+                        segments.push(segment);
+                    }
+                    c => literal.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+                }
+            }
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(literal));
+            }
+            Ok(Printf { segments })
+        }
+
+        /// The `statx` fields that the directives are made from.
+        pub fn stat_mask(&self) -> u32 {
+            self.segments.iter().fold(0, |mask, segment| match segment {
+                Segment::Literal(_) => mask,
+                Segment::Field { field, .. } | Segment::Time { field, .. } => mask | field.stat_mask()
+            })
+        }
+
+        /// Writes out the entry. Paths are written byte for byte, so the result need not be UTF-8.
+        pub fn format(&self, entry: &Entry) -> Vec<u8> {
+            let mut result = Vec::new();
+            for segment in &self.segments {
+                match segment {
+                    Segment::Literal(literal) => result.extend_from_slice(literal),
+                    Segment::Field { field, width, left_align } => {
+                        let rendered = field.render_bytes(entry);
+                        // the width is counted in bytes, as C's printf does
+                        let padding = vec![b' '; width.unwrap_or(0).saturating_sub(rendered.len())];
+                        match left_align {
+                            true => result.extend([rendered, padding].concat()),
+                            false => result.extend([padding, rendered].concat())
+                        }
+                    }
+                    Segment::Time { field, format } => {
+                        // a birth time that the file system does not record is left empty, as GNU find does
+                        let Some(seconds) = field.seconds(entry) else {
+                            continue;
+                        };
+                        let time = match format {
+                            '@' => seconds.to_string(),
+                            '+' => format_time(seconds, "%Y-%m-%d+%H:%M:%S"),
+                            format => format_time(seconds, &format!("%{}", format))
+                        };
+                        result.extend_from_slice(time.as_bytes());
+                    }
+                }
+            }
+            result
+        }
+    }
+
+    fn unescape(chars: &mut std::iter::Peekable<std::str::Chars>) -> Vec<u8> {
+        let escaped = match chars.next() {
+            Some('n') => b'\n',
+            Some('t') => b'\t',
+            Some('r') => b'\r',
+            Some('a') => 0x07,
+            Some('b') => 0x08,
+            Some('f') => 0x0c,
+            Some('v') => 0x0b,
+            Some('\\') => b'\\',
+            Some(digit) if digit.is_digit(8) => {
+                // up to three octal digits, e.g. \0 or \033
+                let mut value = digit.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match chars.next_if(|c| c.is_digit(8)) {
+                        Some(digit) => value = value * 8 + digit.to_digit(8).unwrap(),
+                        None => break
+                    }
+                }
+                // only the low byte is kept, as C does when it stores the value in a char
+                value as u8
+            }
+            Some(other) => return format!("\\{}", other).into_bytes(),
+            None => return b"\\".to_vec()
+        };
+        vec![escaped]
+    }
+}
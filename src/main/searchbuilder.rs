@@ -0,0 +1,262 @@
+pub use self::searchbuilder::{Entries, SearchBuilder, SearchError};
+pub mod searchbuilder {
+    use std::error::Error;
+    use std::fmt;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+    use std::sync::Arc;
+    #[cfg(feature = "async")]
+    use std::sync::Mutex;
+    #[cfg(feature = "async")]
+    use std::task::Waker;
+    use std::thread;
+
+    use crate::main::action::Action;
+    use crate::main::entry::Entry;
+    #[cfg(feature = "async")]
+    use crate::main::entrystream::EntryStream;
+    use crate::main::errorcounts::ErrorCategory;
+    use crate::main::expression::Registry;
+    use crate::main::filesystem::FileSystem;
+    use crate::main::line::Line;
+    use crate::main::logger::Logger;
+    use crate::main::message::Message;
+    use crate::main::params::Params;
+    use crate::main::predicate::{All, Predicate};
+    use crate::main::searcher::Searcher;
+    use crate::main::sink::Sink;
+    use crate::main::stop::StopReason;
+    use crate::main::symlinksetting::SymLinkSetting;
+    use crate::main::test::Test;
+    use crate::main::threadpool::ThreadPool;
+
+    // how many entries can be waiting to be taken from the iterator before the search waits for them
+    const CAPACITY: usize = 1024;
+
+    /// Sets up a search to run from a library, e.g.
+    /// `SearchBuilder::new("/var/log").max_depth(3).filter(Test::Name("syslog".to_string())).build()?`.
+    #[derive(Debug, Clone)]
+    pub struct SearchBuilder {
+        starting_paths: Vec<PathBuf>,
+        max_depth: Option<u32>,
+        min_depth: Option<u32>,
+        filter: Test,
+        registry: Registry,
+        expression: Vec<String>,
+        symlink_setting: SymLinkSetting,
+        threads: usize,
+        file_system: Option<Arc<dyn FileSystem>>
+    }
+
+    impl SearchBuilder {
+        /// A search of everything below `path`, including `path` itself, on as many threads as
+        /// there are CPUs.
+        pub fn new<P: AsRef<Path>>(path: P) -> SearchBuilder {
+            SearchBuilder {
+                starting_paths: vec![path.as_ref().to_path_buf()],
+                max_depth: None,
+                min_depth: None,
+                filter: Test::True,
+                registry: Registry::new(),
+                expression: Vec::new(),
+                symlink_setting: SymLinkSetting::Never,
+                threads: thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1),
+                file_system: None
+            }
+        }
+
+        /// Searches another starting point after the ones before it.
+        pub fn path<P: AsRef<Path>>(mut self, path: P) -> SearchBuilder {
+            self.starting_paths.push(path.as_ref().to_path_buf());
+            self
+        }
+
+        pub fn max_depth(self, max_depth: u32) -> SearchBuilder {
+            SearchBuilder { max_depth: Some(max_depth), ..self }
+        }
+
+        pub fn min_depth(self, min_depth: u32) -> SearchBuilder {
+            SearchBuilder { min_depth: Some(min_depth), ..self }
+        }
+
+        /// Only yields the entries that match `filter`.
+        pub fn filter(self, filter: Test) -> SearchBuilder {
+            SearchBuilder { filter, ..self }
+        }
+
+        /// Lets the expression name `predicate`, e.g. `--larger`.
+        pub fn predicate<N: Into<String>>(self, name: N, predicate: Arc<dyn Predicate>) -> SearchBuilder {
+            SearchBuilder { registry: self.registry.with(name, predicate), ..self }
+        }
+
+        /// Only yields the entries that match an expression as well as the filter, e.g.
+        /// `["--name", "a.txt", "--or", "--larger"]`, which can name the predicates given to `predicate`.
+        pub fn expression<T: Into<String>, I: IntoIterator<Item = T>>(self, expression: I) -> SearchBuilder {
+            SearchBuilder { expression: expression.into_iter().map(Into::into).collect(), ..self }
+        }
+
+        pub fn follow_links(self, symlink_setting: SymLinkSetting) -> SearchBuilder {
+            SearchBuilder { symlink_setting, ..self }
+        }
+
+        /// 1 searches on a single thread, which yields the entries in the order that they are found in.
+        pub fn threads(self, threads: usize) -> SearchBuilder {
+            SearchBuilder { threads: threads.max(1), ..self }
+        }
+
+        /// Searches `file_system` instead of the file system of the OS.
+        pub fn file_system(self, file_system: Arc<dyn FileSystem>) -> SearchBuilder {
+            SearchBuilder { file_system: Some(file_system), ..self }
+        }
+
+        /// Starts the search in the background. Entries are yielded as soon as they are found.
+        /// Fails without searching when the filter is a regular expression that does not compile,
+        /// or when the expression can not be parsed.
+        pub fn build(self) -> Result<Entries, String> {
+            let (sender, receiver) = sync_channel(CAPACITY);
+            let searcher = self.start(Forward { sender })?;
+            Ok(Entries { receiver, searcher })
+        }
+
+        /// Starts the search in the background, like `build`, for async code. The search waits
+        /// while the stream is not being polled, and stops when it is dropped.
+        #[cfg(feature = "async")]
+        pub fn build_stream(self) -> Result<EntryStream, String> {
+            let (sender, receiver) = sync_channel(CAPACITY);
+            let waker = Arc::new(Mutex::new(None));
+            let searcher = self.start(WakingForward { sender: Some(sender), waker: Arc::clone(&waker) })?;
+            Ok(EntryStream::new(receiver, waker, searcher))
+        }
+
+        // Searches on a thread of its own, with every line that the search writes going to `logger`.
+        fn start<L: Logger + Send + 'static>(self, logger: L) -> Result<Arc<Searcher>, String> {
+            let filter = Test::Custom(Arc::new(All::new(vec![self.filter.predicate()?, self.registry.parse(&self.expression, None)?])));
+            let params = Params {
+                symlink_setting: self.symlink_setting,
+                actions: vec![Action::Send],
+                ..Default::default()
+            };
+            let threadpool = (self.threads > 1).then(|| Arc::new(ThreadPool::new(self.threads)));
+            let searcher = Searcher::with_starting_paths(params, self.max_depth, self.min_depth, self.starting_paths, threadpool.clone());
+            let searcher = Arc::new(match self.file_system {
+                Some(file_system) => searcher.with_file_system(file_system),
+                None => searcher
+            });
+            let (sink, _) = Sink::new(CAPACITY, logger);
+            let search = Arc::clone(&searcher);
+            thread::spawn(move || {
+                search.search(filter, &sink);
+                // the pool would drop the jobs that are still queued if it was dropped before they ran
+                if let Some(threadpool) = threadpool {
+                    threadpool.wait();
+                }
+            });
+            Ok(searcher)
+        }
+    }
+
+    /// The entries that a search finds, and the errors that it runs into. The search stops when
+    /// this is dropped.
+    #[derive(Debug)]
+    pub struct Entries {
+        receiver: Receiver<Line>,
+        searcher: Arc<Searcher>
+    }
+
+    impl Iterator for Entries {
+        type Item = Result<Entry, SearchError>;
+
+        fn next(&mut self) -> Option<Result<Entry, SearchError>> {
+            // the channel is closed once every part of the search has finished
+            for line in self.receiver.iter() {
+                match line.message {
+                    Message::Entry(entry) => return Some(Ok(*entry)),
+                    Message::Error(category, message) => return Some(Err(SearchError { category, message })),
+                    _ => {}
+                }
+            }
+            None
+        }
+    }
+
+    impl Drop for Entries {
+        fn drop(&mut self) {
+            self.searcher.stop().stop(StopReason::LimitReached);
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SearchError {
+        category: ErrorCategory,
+        message: String
+    }
+
+    impl SearchError {
+        pub fn category(&self) -> ErrorCategory {
+            self.category
+        }
+    }
+
+    impl fmt::Display for SearchError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.message)
+        }
+    }
+
+    impl Error for SearchError {}
+
+    // hands every line to the iterator instead of writing it
+    struct Forward {
+        sender: SyncSender<Line>
+    }
+
+    impl Logger for Forward {
+        fn log(&mut self, line: Line) {
+            // nothing is waiting for the line once the iterator has been dropped
+            _ = self.sender.send(line);
+        }
+
+        fn log_as_tree(&mut self, _dir_entries: Vec<(String, bool)>, _preceding_str: Option<String>) -> Vec<String> {
+            Vec::new()
+        }
+    }
+
+    // hands every entry to the stream, and wakes the task that is waiting for it
+    #[cfg(feature = "async")]
+    struct WakingForward {
+        // taken when this is dropped, so that the stream sees that the channel is closed once it is woken
+        sender: Option<SyncSender<Line>>,
+        waker: Arc<Mutex<Option<Waker>>>
+    }
+
+    #[cfg(feature = "async")]
+    impl WakingForward {
+        fn wake(&self) {
+            if let Some(waker) = self.waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl Logger for WakingForward {
+        fn log(&mut self, line: Line) {
+            // the search stops straight away once the stream has been dropped
+            if matches!(line.message, Message::Entry(_)) && self.sender.as_ref().is_some_and(|sender| sender.send(line).is_ok()) {
+                self.wake();
+            }
+        }
+
+        fn log_as_tree(&mut self, _dir_entries: Vec<(String, bool)>, _preceding_str: Option<String>) -> Vec<String> {
+            Vec::new()
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl Drop for WakingForward {
+        fn drop(&mut self) {
+            self.sender = None;
+            self.wake();
+        }
+    }
+}
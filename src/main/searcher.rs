@@ -1,165 +1,840 @@
 
 pub use self::searcher::Searcher;
 pub mod searcher {
-    use std::io::ErrorKind;
-    use std::fs::{self, FileType, ReadDir};
-    use std::ops::Deref;
-    use std::{borrow::BorrowMut, cell::RefCell, fmt::Debug, ptr, rc::Rc, cell::Ref};
-    use std::os::fd::{AsRawFd, FromRawFd};
-    use std::os::unix::fs::FileTypeExt;
+    use std::io::{self, ErrorKind};
+    use std::ffi::{OsStr, OsString};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::fmt::Debug;
+    use std::os::unix::ffi::OsStrExt;
     use std::sync::Mutex;
-    use std::path::Path;
-    use regex::Regex;
-    use std::thread;
-    use std::sync::mpsc::channel;
-    use std::sync::{Arc, MutexGuard};
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+    use std::sync::{Arc, Weak};
 
     use crate::main::symlinksetting::SymLinkSetting;
     use crate::main::test::Test;
-    use crate::main::logger::Logger;
+    use crate::main::predicate::Predicate;
     use crate::main::params::Params;
     use crate::main::filedescriptor::FileDescriptor;
     use crate::main::message::Message;
     use crate::main::line::Line;
     use crate::main::debugopts::DebugOpts;
-    use crate::main::threadpool::{self, ThreadPool};
+    use crate::main::threadpool::ThreadPool;
+    use crate::main::action::Action;
+    use crate::main::directory::{Directory, DirectoryEntries, DirectoryEntry};
+    use crate::main::filesystem::{FileSystem, Os};
+    use crate::main::filetype::FileType;
+    use crate::main::entry::Entry;
+    use crate::main::format::long_listing;
+    use crate::main::outputformat::OutputFormat;
+    use crate::main::sink::Sink;
+    use crate::main::errorcounts::{ErrorCategory, ErrorCounts};
+    use crate::main::stop::{Stop, StopReason};
+
+    /// A directory that is being searched, and the directories it was found in. Only kept when
+    /// following symbolic links, which is the only way that a directory can be found inside itself.
+    #[derive(Debug)]
+    struct Ancestor {
+        dev: u64,
+        ino: u64,
+        path: PathBuf,
+        parent: Option<Arc<Ancestor>>
+    }
+
+    // the number of directories that a breadth-first search holds on to for the next level
+    const FRONTIER_CAPACITY: usize = 65536;
+
+    /// What is needed to search a directory.
+    #[derive(Debug)]
+    struct Handles {
+        directory: Opening,
+        // only kept when following symbolic links
+        ancestor: Option<Arc<Ancestor>>,
+        // where subdirectories go when searching breadth-first
+        frontier: Option<Arc<Frontier>>
+    }
+
+    #[derive(Debug)]
+    enum Opening {
+        Open(Directory),
+        // opened by the job that searches it rather than when it is queued, so that directories
+        // that are waiting for a thread do not hold on to a file descriptor each
+        Child { parent: Arc<Directory>, route: Arc<Route>, name: OsString, through_symlink: bool },
+        // for directories that are queued while too many are open, which would otherwise keep
+        // the directory they were found in open until they are searched
+        Route { parent: Arc<Route>, name: OsString, through_symlink: bool }
+    }
+
+    /// The names that lead to a directory from the starting point it was found under, to open it
+    /// again from the closest directory above it that is still open. Its path may be too long to
+    /// open it by, and would follow a symlink that replaced a directory on the way.
+    #[derive(Debug)]
+    struct Route {
+        // only the starting point is held on to, so that there is always somewhere to start from
+        start: Option<Arc<Directory>>,
+        directory: Weak<Directory>,
+        parent: Option<Arc<Route>>,
+        name: OsString,
+        through_symlink: bool
+    }
+
+    impl Route {
+        fn start(directory: &Arc<Directory>) -> Arc<Route> {
+            Arc::new(Route { start: Some(Arc::clone(directory)), directory: Arc::downgrade(directory), parent: None, name: OsString::new(), through_symlink: true })
+        }
+
+        fn child(self: &Arc<Self>, directory: &Arc<Directory>, name: OsString, through_symlink: bool) -> Arc<Route> {
+            Arc::new(Route { start: None, directory: Arc::downgrade(directory), parent: Some(Arc::clone(self)), name, through_symlink })
+        }
+
+        // Opens a directory in this one, going down from the closest directory that is still open.
+        fn open_at(&self, name: &OsStr, through_symlink: bool) -> io::Result<Directory> {
+            let mut closed = Vec::new();
+            let mut route = self;
+            let mut directory = loop {
+                match (route.start.clone().or_else(|| route.directory.upgrade()), &route.parent) {
+                    (Some(directory), _) => break directory,
+                    (None, Some(parent)) => {
+                        closed.push(route);
+                        route = parent;
+                    }
+                    (None, None) => return Err(io::Error::other("the starting point is no longer open"))
+                }
+            };
+            for route in closed.into_iter().rev() {
+                directory = Arc::new(directory.open_at(&route.name, route.through_symlink)?);
+            }
+            directory.open_at(name, through_symlink)
+        }
+    }
+
+    /// A directory that is being searched on this thread, and the entries of it that are left.
+    struct Frame {
+        path: PathBuf,
+        // the name in the directory below it on the stack, to open it again by
+        name: OsString,
+        // `None` once it has been closed to stay under the limit of open directories
+        directory: Option<Arc<Directory>>,
+        route: Arc<Route>,
+        // the device and inode, to check that it is the same directory when it is opened again
+        id: (u64, u64),
+        through_symlink: bool,
+        entries: DirectoryEntries,
+        depth: u32,
+        ancestor: Option<Arc<Ancestor>>,
+        frontier: Option<Arc<Frontier>>,
+        preceding_str: String,
+        sink: Sink,
+        postponed: Option<Postponed>
+    }
+
+    /// A directory that is about to be searched on this thread.
+    struct Descent {
+        path: PathBuf,
+        handles: Handles,
+        preceding_str: String,
+        depth: u32,
+        sink: Sink,
+        postponed: Option<Postponed>
+    }
+
+    /// An entry that is tested once the contents of the directory it names have been, for --delete.
+    struct Postponed {
+        name: OsString,
+        d_type: Option<FileType>,
+        link_type: FileType
+    }
+
+    /// The directories of the next level of a breadth-first search. Once it is full, directories
+    /// are searched depth-first straight away instead, so that memory use is bounded.
+    #[derive(Debug)]
+    struct Frontier {
+        pending: Mutex<Vec<Pending>>,
+        capacity: usize
+    }
+
+    #[derive(Debug)]
+    struct Pending {
+        path: PathBuf,
+        handles: Handles,
+        depth: u32,
+        sink: Sink
+    }
+
+    impl Frontier {
+        fn new(capacity: usize) -> Frontier {
+            Frontier { pending: Mutex::new(Vec::new()), capacity }
+        }
+
+        // gives the directory back if there is no room for it
+        fn push(&self, pending: Pending) -> Result<(), Box<Pending>> {
+            let mut queue = self.pending.lock().unwrap();
+            if queue.len() >= self.capacity {
+                return Err(Box::new(pending));
+            }
+            queue.push(pending);
+            Ok(())
+        }
+
+        fn take(&self) -> Vec<Pending> {
+            std::mem::take(&mut *self.pending.lock().unwrap())
+        }
+    }
 
     #[derive(Debug)]
     pub struct Searcher {
         min_depth: Option<u32>,
         max_depth: Option<u32>,
-        threadpool: Option<Arc<Mutex<ThreadPool>>>,
+        threadpool: Option<Arc<ThreadPool>>,
         params: Params,
-        pub starting_path: String
+        errors: ErrorCounts,
+        stop: Stop,
+        matches: AtomicU64,
+        max_results: Option<u64>,
+        // the metadata that the actions print, which is fetched lazily along with what the test needs
+        stat_mask: u32,
+        // the number of directories that can be open before the ones that are not being read from are closed
+        directory_limit: usize,
+        file_system: Arc<dyn FileSystem>,
+        pub starting_paths: Vec<PathBuf>
+    }
+
+    // Half of the descriptors that the process can open, leaving the rest for output files and
+    // for the threads that are opening directories at the same time.
+    fn directory_limit() -> usize {
+        let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        match unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } {
+            0 if limit.rlim_cur != libc::RLIM_INFINITY => (limit.rlim_cur as usize / 2).max(16),
+            _ => 4096
+        }
     }
 
     impl Searcher {
-        pub fn new(params: Params, max_depth: Option<u32>, min_depth: Option<u32>, starting_path: String, threadpool: Option<Arc<Mutex<ThreadPool>>>) -> Searcher {
+        pub fn new(params: Params, max_depth: Option<u32>, min_depth: Option<u32>, starting_path: String, threadpool: Option<Arc<ThreadPool>>) -> Searcher {
+            Searcher::with_starting_paths(params, max_depth, min_depth, vec![PathBuf::from(starting_path)], threadpool)
+        }
+
+        pub fn with_starting_paths(params: Params, max_depth: Option<u32>, min_depth: Option<u32>, starting_paths: Vec<PathBuf>, threadpool: Option<Arc<ThreadPool>>) -> Searcher {
+            let stat_mask = params.actions.iter().fold(0, |mask, action| mask | action.stat_mask(&params.output_format));
             Searcher {
+                stat_mask,
                 params,
                 max_depth,
                 min_depth,
-                starting_path,
-                threadpool
+                starting_paths,
+                threadpool,
+                errors: ErrorCounts::default(),
+                stop: Stop::default(),
+                matches: AtomicU64::new(0),
+                max_results: None,
+                directory_limit: directory_limit(),
+                file_system: Arc::new(Os)
+            }
+        }
+
+        /// Stops the search once `timeout` has passed, as if it had been interrupted.
+        pub fn with_timeout(self, timeout: Duration) -> Searcher {
+            Searcher {
+                stop: Stop::new(Some(timeout)),
+                ..self
+            }
+        }
+
+        /// Keeps at most about `directory_limit` directories open, rather than half as many as the
+        /// process can have open files.
+        pub fn with_directory_limit(self, directory_limit: usize) -> Searcher {
+            Searcher {
+                directory_limit,
+                ..self
             }
         }
 
-        pub fn search_directory_path(self: Arc<Self>, directory_path: &Path, test: Test, preceding_str: Option<String>, current_depth: Option<u32>, mut lines: Arc<Vec<Line>>) { 
-            let min_depth = self.min_depth;
-            let max_depth = self.max_depth;
-            let params = self.params.clone();
-            let current_depth = current_depth.unwrap_or(0);
-            let read_dir = match fs::read_dir(directory_path) {
-                Ok(res) => {
-                    res
+        /// Fetches the `statx` fields in `stat_mask` as well as those that the actions write, for
+        /// whatever else writes or sorts the matches, such as the columns of --format csv.
+        pub fn with_stat_mask(self, stat_mask: u32) -> Searcher {
+            Searcher {
+                stat_mask: self.stat_mask | stat_mask,
+                ..self
+            }
+        }
+
+        /// Stops the search once actions have been applied to `max_results` matches.
+        pub fn with_max_results(self, max_results: u64) -> Searcher {
+            Searcher {
+                max_results: Some(max_results),
+                ..self
+            }
+        }
+
+        /// Searches `file_system` instead of the file system of the OS. Entries can only be deleted
+        /// from it if it implements `FileSystem::remove`.
+        pub fn with_file_system(self, file_system: Arc<dyn FileSystem>) -> Searcher {
+            Searcher {
+                file_system,
+                ..self
+            }
+        }
+
+        fn file_system(&self) -> &dyn FileSystem {
+            self.file_system.as_ref()
+        }
+
+        // Opens a directory by its path, which the directories in it are then opened relative to.
+        fn open_directory(&self, path: &Path, follow_symlink: bool) -> io::Result<Directory> {
+            Arc::clone(&self.file_system).open_directory(path, follow_symlink)
+        }
+
+        /// The number of matches that actions were applied to.
+        pub fn match_count(&self) -> u64 {
+            let count = self.matches.load(Ordering::SeqCst);
+            self.max_results.map_or(count, |max_results| count.min(max_results))
+        }
+
+        // Whether actions should be applied to another match, which they are not once the limit has been reached
+        // by other threads
+        fn count_match(&self) -> bool {
+            let count = self.matches.fetch_add(1, Ordering::SeqCst) + 1;
+            match self.max_results {
+                Some(max_results) if count > max_results => false,
+                Some(max_results) => {
+                    if count == max_results {
+                        self.stop.stop(StopReason::LimitReached);
+                    }
+                    true
                 }
-                Err(error) if error.kind() == ErrorKind::PermissionDenied => {
-                    let line = format!("rfind: Permission denied for directory name {}", directory_path.to_str().unwrap());
-                    lines.push(Line::new_with_fd(Message::Standard(line), FileDescriptor::StdErr));
+                None => true
+            }
+        }
+
+        pub fn stop(&self) -> &Stop {
+            &self.stop
+        }
+
+        /// Whether an error occurred that should make the search exit with a non-zero status.
+        pub fn has_failed(&self) -> bool {
+            let ignored = match self.params.ignore_readdir_race {
+                true => self.errors.count(ErrorCategory::Vanished),
+                false => 0
+            };
+            self.errors.total() > ignored
+        }
+
+        pub fn errors(&self) -> &ErrorCounts {
+            &self.errors
+        }
+
+        fn record_failure(&self, category: ErrorCategory, line: String, sink: &Sink) {
+            self.errors.record(category);
+            sink.send(Line::new_with_fd(Message::Error(category, line), FileDescriptor::StdErr));
+        }
+
+        // For an entry that was read from a directory.
+        fn record_error(&self, path: &Path, error: &io::Error, sink: &Sink) {
+            let category = ErrorCategory::of_entry(error);
+            let line = match category {
+                // removed by something else while it was being searched, which is to be expected in a busy tree
+                ErrorCategory::Vanished => format!("rfind: warning: {} vanished during the search: {}", path.to_string_lossy(), error),
+                _ => format!("rfind: {}: {}", path.to_string_lossy(), error)
+            };
+            self.record_failure(category, line, sink);
+        }
+
+        /// Searches every starting point in turn, with the same test. A test that can not be made,
+        /// such as a regular expression that does not compile, is a failure, and nothing is searched.
+        pub fn search(self: Arc<Self>, test: Test, sink: &Sink) {
+            let test = match test.predicate() {
+                Ok(test) => test,
+                Err(error) => return self.record_failure(ErrorCategory::Io, format!("rfind: {}", error), sink)
+            };
+            for starting_path in &self.starting_paths {
+                if self.stop.is_stopped() {
                     return;
                 }
-                Err(_) => {
-                    let line = format!("rfind: An error occurred when attempting to read the {} directory", directory_path.to_str().unwrap());
-                    lines.push(Line::new_with_fd(Message::Standard(line), FileDescriptor::StdErr));
+                Arc::clone(&self).search_starting_point(starting_path, &test, sink);
+            }
+        }
+
+        // Tests a starting point at depth 0, and searches it if it is a directory. It is tested after
+        // its contents when searching depth-first.
+        fn search_starting_point(self: Arc<Self>, starting_path: &Path, test: &Arc<dyn Predicate>, sink: &Sink) {
+            let follow = self.params.symlink_setting != SymLinkSetting::Never;
+            let (entry, link_type) = match Entry::starting_point(starting_path, follow, self.file_system()).and_then(|entry| Ok((entry.link_type()?, entry))) {
+                Ok((link_type, entry)) => (entry, link_type),
+                Err(error) => {
+                    self.report_unreadable_directory(starting_path, error, sink);
                     return;
                 }
             };
-            let mut read_dir_iter = read_dir.peekable();
-            let rc_ref = Arc::clone(&lines);
-            while let Some(ele) = read_dir_iter.next() {
-                let mut preceding_str = preceding_str.clone().unwrap_or(String::new()).clone();
-                if params.debug_opts.is_some() {
-                    let debug_opts = params.debug_opts.as_ref().unwrap();
-                    if read_dir_iter.peek().is_some() && *debug_opts == DebugOpts::Tree {
-                        preceding_str.push_str("├── ")
-                    }
-                    else if read_dir_iter.peek().is_none() && *debug_opts == DebugOpts::Tree {
-                        preceding_str.push_str("└── ") 
+            let descend = entry.file_type().is_ok_and(|file_type| file_type.is_dir()) && self.max_depth != Some(0);
+            if !self.params.depth_first {
+                self.evaluate(&entry, None, &link_type, test.as_ref(), sink);
+            }
+            if descend {
+                match self.params.breadth_first {
+                    true => Arc::clone(&self).search_breadth_first(starting_path, Arc::clone(test), sink.clone()),
+                    false => Arc::clone(&self).search_tree(starting_path, Arc::clone(test), None, None, sink.clone())
+                }
+            }
+            if self.params.depth_first && !self.stop.is_stopped() {
+                self.evaluate(&entry, None, &link_type, test.as_ref(), sink);
+            }
+        }
+
+        pub fn search_directory_path(self: Arc<Self>, directory_path: &Path, test: Test, preceding_str: Option<String>, current_depth: Option<u32>, sink: Sink) { 
+            match test.predicate() {
+                Ok(test) => self.search_tree(directory_path, test, preceding_str, current_depth, sink),
+                Err(error) => self.record_failure(ErrorCategory::Io, format!("rfind: {}", error), &sink)
+            }
+        }
+
+        fn search_tree(self: Arc<Self>, directory_path: &Path, test: Arc<dyn Predicate>, preceding_str: Option<String>, current_depth: Option<u32>, sink: Sink) {
+            if let Some(handles) = self.open_starting_point(directory_path, &sink) {
+                self.search_directory(directory_path, handles, test, preceding_str, current_depth, sink);
+            }
+        }
+
+        // Searches the directories of each level in parallel, and waits for all of them before
+        // starting on the next level.
+        fn search_breadth_first(self: Arc<Self>, directory_path: &Path, test: Arc<dyn Predicate>, sink: Sink) {
+            let Some(handles) = self.open_starting_point(directory_path, &sink) else {
+                return;
+            };
+            let mut level = vec![Pending { path: directory_path.to_path_buf(), handles, depth: 0, sink }];
+            while !level.is_empty() && !self.stop.is_stopped() {
+                let next_level = Arc::new(Frontier::new(FRONTIER_CAPACITY));
+                for Pending { path, mut handles, depth, sink } in level {
+                    handles.frontier = Some(Arc::clone(&next_level));
+                    let (searcher, test) = (Arc::clone(&self), Arc::clone(&test));
+                    match &self.threadpool {
+                        Some(threadpool) => threadpool.execute(move || searcher.search_directory(&path, handles, test, None, Some(depth), sink)),
+                        None => searcher.search_directory(&path, handles, test, None, Some(depth), sink)
                     }
                 }
-                let ele = ele.unwrap();
-                let file_name = ele.file_name();
-                let file_type = ele.file_type().unwrap();
-
-                if file_type.is_symlink() && params.symlink_setting == crate::main::symlinksetting::SymLinkSetting::Follow {
-                    // navigate to the file pointed to by the symlink
-                    let file_referred_to_by_symlink = fs::read_link(ele.path());
-                    _ = match file_referred_to_by_symlink {
-                        Ok(file_referred_to_by_symlink_unwrapped) => {
-                            let line = format!("{}{}", preceding_str, file_referred_to_by_symlink_unwrapped.to_str().unwrap());
-                            lines.push(Line::new_with_fd(Message::Standard(line), FileDescriptor::StdOut));
-                            continue;
+                if let Some(threadpool) = &self.threadpool {
+                    threadpool.wait();
+                }
+                level = next_level.take();
+            }
+        }
+
+        fn open_starting_point(&self, directory_path: &Path, sink: &Sink) -> Option<Handles> {
+            // a starting point that is a link is only searched if links on the command line are followed
+            let is_symlink = self.file_system().metadata(directory_path, false).is_ok_and(|stat| FileType::from_mode(stat.mode).is_symlink());
+            if is_symlink && self.params.symlink_setting == SymLinkSetting::Never {
+                return None;
+            }
+            let directory = match self.open_directory(directory_path, true) {
+                Ok(directory) => directory,
+                Err(error) => {
+                    self.report_unreadable_directory(directory_path, error, sink);
+                    return None;
+                }
+            };
+            let ancestor = match self.params.symlink_setting {
+                SymLinkSetting::Follow => match self.file_system().metadata(directory_path, true) {
+                    Ok(stat) => Some(Arc::new(Ancestor { dev: stat.dev, ino: stat.ino, path: directory_path.to_path_buf(), parent: None })),
+                    Err(_) => None
+                },
+                _ => None
+            };
+            Some(Handles { directory: Opening::Open(directory), ancestor, frontier: None })
+        }
+
+        fn report_unreadable_directory(&self, directory_path: &Path, error: io::Error, sink: &Sink) {
+            let line = match error.kind() {
+                ErrorKind::PermissionDenied => format!("rfind: Permission denied for directory name {}", directory_path.to_string_lossy()),
+                _ => format!("rfind: An error occurred when attempting to read the {} directory", directory_path.to_string_lossy())
+            };
+            self.record_failure(ErrorCategory::of(&error), line, sink);
+        }
+
+        fn search_directory(self: Arc<Self>, directory_path: &Path, handles: Handles, test: Arc<dyn Predicate>, preceding_str: Option<String>, current_depth: Option<u32>, sink: Sink) {
+            // directories that are searched on this thread go on an explicit stack rather than being
+            // recursed into, so that how deep a tree can be is not limited by the native stack
+            let mut stack: Vec<Frame> = Vec::new();
+            let mut next = Some(Descent {
+                path: directory_path.to_path_buf(),
+                handles,
+                preceding_str: preceding_str.unwrap_or_default(),
+                depth: current_depth.unwrap_or(0),
+                sink,
+                postponed: None
+            });
+            loop {
+                // jobs that were queued before the search was stopped return straight away
+                if self.stop.is_stopped() {
+                    return;
+                }
+                if let Some(descent) = next.take() {
+                    if let Some(frame) = self.open_frame(descent, &mut stack) {
+                        stack.push(frame);
+                        if Directory::open_count() > self.directory_limit {
+                            Searcher::close_directories(&mut stack);
                         }
-                        Err(error) if error.kind() == ErrorKind::NotFound && read_dir_iter.peek().is_some() => {
-                            let line = format!("{}Broken symlink: {}", preceding_str, ele.path().to_str().unwrap());
-                            lines.push(Line::new_with_fd(Message::Standard(line), FileDescriptor::StdErr));
-                            continue;
+                    }
+                    continue;
+                }
+                let Some(frame) = stack.last_mut() else {
+                    return;
+                };
+                match frame.entries.next() {
+                    Some(Ok(directory_entry)) => next = self.search_entry(&mut stack, directory_entry, &test),
+                    // a directory that is removed once it is open can no longer be read
+                    Some(Err(error)) => self.report_open_error(&frame.path, error, &frame.sink),
+                    None => {
+                        let frame = stack.pop().unwrap();
+                        if let Some(parent) = stack.last_mut() {
+                            if parent.entries.has_more() || frame.postponed.is_some() {
+                                Searcher::reopen_parent(&frame, parent);
+                            }
                         }
-                        Err(_) => {
-                            unreachable!("We have handled both cases where read_link would result in an error, so this should be unreachable");
+                        if let Some(postponed) = frame.postponed {
+                            drop(frame.directory);
+                            self.search_postponed(&mut stack, postponed, test.as_ref());
                         }
                     }
                 }
+            }
+        }
 
-                // todo make operator logic, by default all tests have to pass to return a find.
-                // todo
-                // self.logger.lock().unwrap().log(LogLine::StdOut(directory_path.join(file_name.clone()).to_str().unwrap().to_string()));
-                let line_to_log;
-                let test = test.clone();
-                line_to_log = match test {
-                    Test::Name(ref name) if file_name.to_str().unwrap() == name => true,
-                    Test::Types(ref provided_file_type) if 
-                    (file_type.is_block_device() && provided_file_type.contains('b')) &&
-                    (file_type.is_char_device() && provided_file_type.contains('c')) &&
-                    (file_type.is_dir() && provided_file_type.contains('d')) &&
-                    (file_type.is_file() && provided_file_type.contains('f')) &&
-                    (file_type.is_fifo() && provided_file_type.contains('p')) &&
-                    (file_type.is_symlink() && provided_file_type.contains('l') && params.symlink_setting != SymLinkSetting::Follow) &&
-                    (file_type.is_socket() && provided_file_type.contains('s')) => true,
-                    Test::Regex(ref regex) => {
-                        let re = Regex::new(&format!(r"{}", regex).to_string()).unwrap();
-
-                        re.captures(file_name.to_str().unwrap()).unwrap();
-                        true
-                    } 
-                    _ => false,
-                };
-                if line_to_log {
-                    if (min_depth.is_some() && current_depth > min_depth.unwrap()) || min_depth.is_none() {
-                        let mut b = lines.deref();
-                        b.push(Line::new_with_fd(Message::Standard(directory_path.join(&file_name).to_str().unwrap().to_string()), FileDescriptor::StdOut));
-                        lines.push(Line::new_with_fd(Message::Standard(directory_path.join(file_name).to_str().unwrap().to_string()), FileDescriptor::StdOut));
-                        continue;
-                    }
+        fn open_frame(&self, descent: Descent, stack: &mut [Frame]) -> Option<Frame> {
+            let Descent { path, handles, preceding_str, depth, sink, postponed } = descent;
+            let Handles { directory, ancestor, frontier } = handles;
+            let (directory, parent_route, name, through_symlink) = match directory {
+                // a starting point, which is never closed
+                Opening::Open(directory) => (Ok(directory), None, OsString::new(), true),
+                // a link is only descended into when following links, which means going wherever it points
+                Opening::Child { parent, route, name, through_symlink } => {
+                    let directory = Searcher::retry_when_out_of_descriptors(stack, || parent.open_at(&name, through_symlink));
+                    (directory, Some(route), name, through_symlink)
+                }
+                Opening::Route { parent, name, through_symlink } => {
+                    let directory = Searcher::retry_when_out_of_descriptors(stack, || parent.open_at(&name, through_symlink));
+                    (directory, Some(parent), name, through_symlink)
+                }
+            };
+            let directory = match directory {
+                Ok(directory) => directory,
+                Err(error) => {
+                    self.report_open_error(&path, error, &sink);
+                    return None;
+                }
+            };
+            let stat = match directory.stat() {
+                Ok(stat) => stat,
+                Err(error) => {
+                    self.report_open_error(&path, error, &sink);
+                    return None;
+                }
+            };
+            let directory = Arc::new(directory);
+            let route = match parent_route {
+                Some(parent_route) => parent_route.child(&directory, name.clone(), through_symlink),
+                None => Route::start(&directory)
+            };
+            Some(Frame {
+                path,
+                name,
+                directory: Some(Arc::clone(&directory)),
+                route,
+                id: (stat.dev, stat.ino),
+                through_symlink,
+                entries: DirectoryEntries::new(directory),
+                depth,
+                ancestor,
+                frontier,
+                preceding_str,
+                sink,
+                postponed
+            })
+        }
+
+        // Closes every directory on the stack but the one being searched and the first, which is
+        // where the others are opened again from if they can not be opened through `..`. The ones
+        // that are open are always at the top, so this stops at the first one that is closed. The
+        // entries that are left are read first, as they can not be read once it is closed.
+        fn close_directories(stack: &mut [Frame]) {
+            let Some(top) = stack.len().checked_sub(1) else {
+                return;
+            };
+            for frame in stack[1.min(top)..top].iter_mut().rev() {
+                if frame.directory.take().is_none() {
+                    break;
+                }
+                frame.entries.read_all();
+            }
+        }
+
+        // Opens the directory that a directory was found in through its `..`, which only leads
+        // back there if it was not reached through a symlink.
+        fn reopen_parent(frame: &Frame, parent: &mut Frame) {
+            if parent.directory.is_some() || frame.through_symlink {
+                return;
+            }
+            if let Some(directory) = &frame.directory {
+                parent.directory = directory.open_at(OsStr::new(".."), false).ok()
+                    .filter(|directory| Searcher::is_same_directory(directory, parent.id))
+                    .map(Arc::new);
+            }
+        }
+
+        fn is_same_directory(directory: &Directory, id: (u64, u64)) -> bool {
+            directory.stat().is_ok_and(|stat| (stat.dev, stat.ino) == id)
+        }
+
+        // The fd limit can still be reached by other threads, or by other processes for ENFILE.
+        fn retry_when_out_of_descriptors<F: Fn() -> io::Result<Directory>>(stack: &mut [Frame], open: F) -> io::Result<Directory> {
+            match open() {
+                Err(error) if matches!(error.raw_os_error(), Some(libc::EMFILE) | Some(libc::ENFILE)) => {
+                    Searcher::close_directories(stack);
+                    open()
+                }
+                result => result
+            }
+        }
+
+        // Opens a directory on the stack again if it was closed, relative to the closest directory
+        // below it that is still open. Its path may be too long to open it by. It has to be the
+        // same directory as before, or --delete could remove files outside of the tree.
+        fn frame_directory(stack: &mut [Frame], index: usize) -> io::Result<Arc<Directory>> {
+            if let Some(directory) = &stack[index].directory {
+                return Ok(Arc::clone(directory));
+            }
+            // the first frame is never closed
+            let open = (0..index).rev().find(|i| stack[*i].directory.is_some()).unwrap_or(0);
+            let mut directory = Arc::clone(stack[open].directory.as_ref().unwrap());
+            for frame in &stack[open + 1..=index] {
+                directory = Arc::new(directory.open_at(&frame.name, frame.through_symlink)?);
+            }
+            if !Searcher::is_same_directory(&directory, stack[index].id) {
+                return Err(io::Error::other("the directory was moved while it was being searched"));
+            }
+            stack[index].directory = Some(Arc::clone(&directory));
+            Ok(directory)
+        }
+
+        // Tests and applies the actions to an entry of the directory at the top of the stack. A
+        // directory to search next on this thread is given back.
+        fn search_entry(self: &Arc<Self>, stack: &mut [Frame], directory_entry: DirectoryEntry, test: &Arc<dyn Predicate>) -> Option<Descent> {
+            let index = stack.len() - 1;
+            let directory = match Searcher::frame_directory(stack, index) {
+                Ok(directory) => directory,
+                Err(error) => {
+                    let frame = &mut stack[index];
+                    self.report_open_error(&frame.path, error, &frame.sink);
+                    // the rest of the entries can not be looked at without it
+                    frame.entries = DirectoryEntries::default();
+                    return None;
+                }
+            };
+            let frame = &mut stack[index];
+            let params = &self.params;
+            let mut preceding_str = frame.preceding_str.clone();
+            let is_last = !frame.entries.has_more();
+            if params.debug_opts == Some(DebugOpts::Tree) {
+                preceding_str.push_str(if is_last { "└── " } else { "├── " });
+            }
+            let file_name = directory_entry.name;
+            let follow = params.symlink_setting == SymLinkSetting::Follow;
+            let stat_mask = self.stat_mask | test.stat_mask();
+            let entry = Entry::found(Arc::clone(&directory), frame.path.join(&file_name), frame.depth + 1, directory_entry.file_type, follow, stat_mask);
+            // only stat'ed when the file system does not give the type of the entry
+            let link_type = match entry.link_type() {
+                Ok(link_type) => link_type,
+                Err(error) => {
+                    self.record_error(&entry.path, &error, &frame.sink);
+                    return None;
+                }
+            };
+            // the type of the file itself, which is what tests look at and what decides whether to descend.
+            // a broken link, or one that points back at itself, is treated as a link.
+            let file_type = entry.file_type().unwrap_or(link_type);
+
+            let mut descend = file_type.is_dir() && self.max_depth.is_none_or(|max_depth| entry.depth < max_depth);
+            let mut child_ancestor = None;
+            if descend && follow {
+                match self.enter_directory(frame.ancestor.as_ref(), &directory, &file_name, &entry.path, &frame.sink) {
+                    Some(new_ancestor) => child_ancestor = new_ancestor,
+                    None => descend = false
+                }
+            }
+            let child_preceding_str = match is_last {
+                true => format!("{}  ", preceding_str),
+                false => format!("{}| ", preceding_str)
+            };
+            let through_symlink = link_type.is_symlink();
+            if descend && params.depth_first {
+                // the contents of a directory have to be processed before the directory itself,
+                // so it is searched on this thread instead of being handed to the threadpool.
+                return Some(Descent {
+                    path: entry.path.clone(),
+                    handles: Handles {
+                        directory: Opening::Child { parent: directory, route: Arc::clone(&frame.route), name: file_name.clone(), through_symlink },
+                        ancestor: child_ancestor,
+                        frontier: None
+                    },
+                    preceding_str: child_preceding_str,
+                    depth: frame.depth + 1,
+                    sink: frame.sink.child(),
+                    postponed: Some(Postponed { name: file_name, d_type: directory_entry.file_type, link_type })
+                });
+            }
+
+            // todo make operator logic, by default all tests have to pass to return a find.
+            self.evaluate(&entry, Some(&directory), &link_type, test.as_ref(), &frame.sink);
+            if !descend {
+                return None;
+            }
+            // a queued directory keeps its parent open until it is searched, unless too many directories
+            // are open already. One that is searched on this thread straight away is opened from it anyway.
+            let queued = frame.frontier.is_some() || self.threadpool.is_some();
+            let route = Arc::clone(&frame.route);
+            let opening = match !queued || Directory::open_count() < self.directory_limit {
+                true => Opening::Child { parent: directory, route, name: file_name, through_symlink },
+                false => Opening::Route { parent: route, name: file_name, through_symlink }
+            };
+            let mut pending = Pending {
+                path: entry.path.clone(),
+                handles: Handles { directory: opening, ancestor: child_ancestor, frontier: None },
+                depth: frame.depth + 1,
+                sink: frame.sink.child()
+            };
+            if let Some(frontier) = &frame.frontier {
+                // otherwise it is searched depth-first, like it would be without --bfs
+                match frontier.push(pending) {
+                    Ok(()) => return None,
+                    Err(returned) => pending = *returned
                 }
-                if file_type.is_dir() && ((max_depth.is_some() && current_depth < max_depth.unwrap()) || max_depth.is_none()) {
-                    let file_name = ele.file_name();
-                    let file_name: &str = file_name.to_str().unwrap();
-                    let directory_path = directory_path.join(file_name);
+            }
+            let Pending { path, handles, depth, sink } = pending;
+            let Some(threadpool) = &self.threadpool else {
+                return Some(Descent { path, handles, preceding_str: child_preceding_str, depth, sink, postponed: None });
+            };
+            let (searcher, test) = (Arc::clone(self), Arc::clone(test));
+            threadpool.execute(move || searcher.search_directory(&path, handles, test, Some(child_preceding_str), Some(depth), sink));
+            None
+        }
+
+        // Tests a directory whose contents have been searched, now that they have been.
+        fn search_postponed(&self, stack: &mut [Frame], postponed: Postponed, test: &dyn Predicate) {
+            let Some(index) = stack.len().checked_sub(1) else {
+                return;
+            };
+            let directory = match Searcher::frame_directory(stack, index) {
+                Ok(directory) => directory,
+                Err(error) => {
+                    let frame = &stack[index];
+                    self.report_open_error(&frame.path, error, &frame.sink);
+                    return;
+                }
+            };
+            let frame = &stack[index];
+            let Postponed { name, d_type, link_type } = postponed;
+            let follow = self.params.symlink_setting == SymLinkSetting::Follow;
+            let entry = Entry::found(Arc::clone(&directory), frame.path.join(&name), frame.depth + 1, d_type, follow, self.stat_mask | test.stat_mask());
+            self.evaluate(&entry, Some(&directory), &link_type, test, &frame.sink);
+        }
+
+        // Applies the actions to an entry that matched, at a depth that actions are applied at.
+        // `directory` is the one that the entry was found in, which a starting point does not have.
+        fn evaluate(&self, entry: &Entry, directory: Option<&Directory>, link_type: &FileType, test: &dyn Predicate, sink: &Sink) {
+            if test.matches(entry) && self.min_depth.is_none_or(|min_depth| entry.depth >= min_depth) && self.count_match() {
+                self.apply_actions(entry, directory, link_type, sink);
+            }
+        }
 
-                    let preceding_str_2: String;
-                    match read_dir_iter.peek() {
-                        Some(_) => preceding_str_2 = format!("{}| ", preceding_str),
-                        None => preceding_str_2 = format!("{}  ", preceding_str)
+        /// Checks that a directory is not one of the directories it was found in. The outer `None`
+        /// means that it is, and that the directory should not be searched.
+        fn enter_directory(&self, ancestor: Option<&Arc<Ancestor>>, directory: &Directory, file_name: &OsStr, child_directory_path: &Path, sink: &Sink) -> Option<Option<Arc<Ancestor>>> {
+            let stat = match directory.stat_at(file_name, true) {
+                Ok(stat) => stat,
+                Err(error) => {
+                    self.record_error(child_directory_path, &error, sink);
+                    return None;
+                }
+            };
+            let mut next = ancestor;
+            while let Some(current) = next {
+                if current.dev == stat.dev && current.ino == stat.ino {
+                    self.record_failure(ErrorCategory::Loop, format!("rfind: File system loop detected; '{}' is part of the same file system loop as '{}'.",
+                        child_directory_path.to_string_lossy(), current.path.to_string_lossy()), sink);
+                    return None;
+                }
+                next = current.parent.as_ref();
+            }
+            Some(Some(Arc::new(Ancestor {
+                dev: stat.dev,
+                ino: stat.ino,
+                path: child_directory_path.to_path_buf(),
+                parent: ancestor.cloned()
+            })))
+        }
+
+        // For a directory that was read from the one it is in.
+        fn report_open_error(&self, directory_path: &Path, error: io::Error, sink: &Sink) {
+            match ErrorCategory::of_entry(&error) {
+                ErrorCategory::Vanished => self.record_error(directory_path, &error, sink),
+                _ => self.report_unreadable_directory(directory_path, error, sink)
+            }
+        }
+
+        fn apply_actions(&self, entry: &Entry, directory: Option<&Directory>, file_type: &FileType, sink: &Sink) {
+            let path = &entry.path;
+            let mut lines = Vec::new();
+            for action in &self.params.actions {
+                match action {
+                    Action::Print(fd) if self.params.output_format == OutputFormat::Standard => {
+                        lines.push(Line::new_with_fd(Message::Path(path.clone()), *fd));
                     }
-                    type SearcherFn = fn(Arc<Searcher>, &Path, Test, Option<String>, Option<u32>, Arc<Vec<Line>>);
-                    let searcher_fn: SearcherFn = Searcher::search_directory_path;
-
-
-                    // if arc.clone works the way i think it does, then the reference count is
-                    // incremented by 1, instead of performing a deep copy.
-                    let self_ref = Arc::clone(&self);
-                    let some_rc = Arc::clone(&rc_ref);
-                    if self_ref.threadpool.is_some() {
-                        self_ref.threadpool.clone().unwrap().lock().unwrap().execute(move || {
-                            searcher_fn(self_ref, directory_path.as_path(), test, Some(preceding_str_2), Some(current_depth + 1), some_rc);
-                        });
+                    Action::Print0(fd) => {
+                        lines.push(Line::new_with_fd(Message::Raw([path.as_os_str().as_bytes(), b"\0"].concat()), *fd));
                     }
-                    else {
-                        self_ref.search_directory_path(&directory_path, test, Some(preceding_str_2), Some(current_depth + 1), some_rc);
+                    Action::Send => lines.push(Line::new(Message::Entry(Box::new(entry.detached())))),
+                    Action::Delete => {
+                        let result = match directory {
+                            Some(directory) => directory.unlink_at(entry.name(), file_type.is_dir()),
+                            // a starting point is removed by its path, unless it is `.` or `..`, as find does
+                            None if path.file_name().is_none() => Ok(()),
+                            None => self.file_system.remove(path, file_type.is_dir())
+                        };
+                        if let Err(error) = result {
+                            let category = match directory {
+                                Some(_) => ErrorCategory::of_entry(&error),
+                                None => ErrorCategory::of(&error)
+                            };
+                            match category {
+                                ErrorCategory::Vanished => self.record_error(path, &error, sink),
+                                category => self.record_failure(category, format!("rfind: cannot delete {}: {}", path.to_string_lossy(), error), sink)
+                            }
+                        }
+                    }
+                    Action::Print(fd) | Action::Printf(fd, _) | Action::Ls(fd) => {
+                        // the entry that is printed in another format has the fields of every column
+                        let stat_mask = match action {
+                            Action::Print(_) => self.stat_mask,
+                            _ => action.stat_mask(&self.params.output_format)
+                        };
+                        if stat_mask != 0 {
+                            if let Err(error) = entry.try_stat() {
+                                self.record_error(path, &error, sink);
+                                continue;
+                            }
+                        }
+                        let message = match action {
+                            // the target of a symlink is read relative to the directory while it is at hand
+                            Action::Print(_) => {
+                                entry.link_target();
+                                Message::Entry(Box::new(entry.detached()))
+                            }
+                            Action::Printf(_, printf) => Message::Raw(printf.format(entry)),
+                            _ => Message::Standard(long_listing(entry))
+                        };
+                        lines.push(Line::new_with_fd(message, *fd));
                     }
                 }
             }
+            // the sort key is fetched along with what the actions need
+            sink.send_match(path, Some(entry), lines);
         }
     }
 }
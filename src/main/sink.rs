@@ -0,0 +1,173 @@
+pub use self::sink::Sink;
+pub mod sink {
+    use std::collections::{HashMap, VecDeque};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+
+    use crate::main::entry::Entry;
+    use crate::main::line::Line;
+    use crate::main::logger::Logger;
+    use crate::main::order::{Order, SortKey};
+
+    /// Where the searcher sends the lines it produces. Lines go through a bounded channel to a
+    /// single output thread that logs them as they arrive, so memory use does not grow with the
+    /// number of matches. The output thread finishes, and gives back its logger, once every clone
+    /// of the sink has been dropped.
+    ///
+    /// With `Order::Stable` every directory that is searched gets its own node through
+    /// `Sink::child`, and the output thread puts the lines of a node where the sequential search
+    /// would have written them. A node is complete once every sink for it has been dropped.
+    #[derive(Debug, Clone)]
+    pub struct Sink {
+        sender: SyncSender<Record>,
+        order: Order,
+        node: Option<Arc<Node>>
+    }
+
+    #[derive(Debug)]
+    enum Record {
+        Line(u64, Line),
+        // the lines for one match
+        Match(SortKey, Vec<Line>),
+        // a child node goes between the lines that its parent sent before and after it
+        Child(u64, u64),
+        Done(u64)
+    }
+
+    #[derive(Debug)]
+    struct Node {
+        id: u64,
+        next_id: Arc<AtomicU64>,
+        sender: SyncSender<Record>
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            _ = self.sender.send(Record::Done(self.id));
+        }
+    }
+
+    impl Sink {
+        pub fn new<L: Logger + Send + 'static>(capacity: usize, logger: L) -> (Sink, JoinHandle<L>) {
+            Sink::with_order(capacity, logger, Order::Traversal)
+        }
+
+        pub fn with_order<L: Logger + Send + 'static>(capacity: usize, mut logger: L, order: Order) -> (Sink, JoinHandle<L>) {
+            let (sender, receiver) = sync_channel::<Record>(capacity);
+            let output_thread = thread::spawn(move || {
+                match order {
+                    Order::Traversal => {
+                        for record in receiver {
+                            if let Record::Line(_, line) = record {
+                                logger.log(line);
+                            }
+                        }
+                    }
+                    Order::Stable => log_in_stable_order(receiver, &mut logger),
+                    Order::Sorted(..) => {
+                        let mut matches = Vec::new();
+                        for record in receiver {
+                            match record {
+                                Record::Match(key, lines) => matches.push((key, lines)),
+                                Record::Line(_, line) => logger.log(line),
+                                _ => {}
+                            }
+                        }
+                        matches.sort_by(|(a, _), (b, _)| order.compare(a, b));
+                        for line in matches.into_iter().flat_map(|(_, lines)| lines) {
+                            logger.log(line);
+                        }
+                    }
+                }
+                logger
+            });
+            let node = (order == Order::Stable).then(|| Arc::new(Node {
+                id: 0,
+                next_id: Arc::new(AtomicU64::new(1)),
+                sender: sender.clone()
+            }));
+            (Sink { sender, order, node }, output_thread)
+        }
+
+        pub fn send(&self, line: Line) {
+            // the output thread only stops once every sink is gone, so this cannot fail
+            // unless it panicked, in which case there is nowhere left to send the line
+            _ = self.sender.send(Record::Line(self.node.as_ref().map_or(0, |node| node.id), line));
+        }
+
+        /// Sends the lines that the actions wrote for one match, which are kept together when sorting.
+        pub fn send_match(&self, path: &Path, entry: Option<&Entry>, lines: Vec<Line>) {
+            match self.order {
+                Order::Sorted(sort_by, _) => _ = self.sender.send(Record::Match(sort_by.key(path, entry), lines)),
+                _ => lines.into_iter().for_each(|line| self.send(line))
+            }
+        }
+
+        /// A sink for a directory that is about to be searched.
+        pub fn child(&self) -> Sink {
+            let Some(node) = &self.node else {
+                return self.clone();
+            };
+            let id = node.next_id.fetch_add(1, Ordering::Relaxed);
+            _ = self.sender.send(Record::Child(node.id, id));
+            Sink {
+                sender: self.sender.clone(),
+                order: self.order,
+                node: Some(Arc::new(Node {
+                    id,
+                    next_id: Arc::clone(&node.next_id),
+                    sender: self.sender.clone()
+                }))
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct Pending {
+        items: VecDeque<Item>,
+        done: bool
+    }
+
+    enum Item {
+        Line(Line),
+        Child(u64)
+    }
+
+    // Lines are logged as soon as everything that comes before them in depth-first order has been
+    // logged. The stack holds the node being logged and the nodes it is nested in.
+    fn log_in_stable_order<L: Logger>(receiver: Receiver<Record>, logger: &mut L) {
+        let mut pending: HashMap<u64, Pending> = HashMap::new();
+        let mut stack = vec![0];
+        for record in receiver {
+            match record {
+                Record::Line(id, line) => pending.entry(id).or_default().items.push_back(Item::Line(line)),
+                Record::Child(parent, id) => pending.entry(parent).or_default().items.push_back(Item::Child(id)),
+                Record::Done(id) => pending.entry(id).or_default().done = true,
+                Record::Match(_, lines) => lines.into_iter().for_each(|line| logger.log(line))
+            }
+            while let Some(&id) = stack.last() {
+                let node = pending.entry(id).or_default();
+                match node.items.pop_front() {
+                    Some(Item::Line(line)) => logger.log(line),
+                    Some(Item::Child(child)) => stack.push(child),
+                    None if node.done => {
+                        pending.remove(&id);
+                        stack.pop();
+                    }
+                    None => break
+                }
+            }
+        }
+        // only left over if a search job panicked, in which case its lines are logged in any order
+        for (_, node) in pending {
+            for item in node.items {
+                if let Item::Line(line) = item {
+                    logger.log(line);
+                }
+            }
+        }
+    }
+}
@@ -1,10 +1,13 @@
-pub use self::standardlogger::StandardLogger;
+pub use self::standardlogger::{write_to_file_descriptor, StandardLogger};
 pub mod standardlogger {
     use crate::main::*;
     use crate::main::line::Line;
     use crate::main::logger::Logger;
+    use crate::main::message::Message;
+    use crate::main::filedescriptor::FileDescriptor;
+    use std::mem::ManuallyDrop;
 
-    #[derive(Debug)]
+    #[derive(Debug, Default)]
     pub struct StandardLogger { }
 
     impl StandardLogger {
@@ -16,38 +19,44 @@ pub mod standardlogger {
 
     impl Logger for StandardLogger {
         fn log(&mut self, line: Line) { 
-            let str_message = line.message.get_contained_message();
-            _ = match line.file_descriptor {
-                Some(fd) if (fd as i32 == 1) => {
-                    println!("{}", str_message);
-                }
-                Some(fd) if (fd as i32 == 2) => {
-                    eprintln!("{}", str_message);
-                }
-                Some(fd) => {
-                    let x = fd as i32;
-                    let mut f = unsafe { File::from_raw_fd(x) };
-                    write!(&mut f, "{}", str_message).unwrap();
-                }
-                None => {
-                    println!("{}", 1);
-                    let mut f = unsafe { File::from_raw_fd(1) };
-                    write!(&mut f, "{}", str_message).unwrap();
-                }
+            let mut bytes = line.message.bytes().into_owned();
+            if !matches!(line.message, Message::Raw(_)) {
+                bytes.push(b'\n');
             }
+            write_to_file_descriptor(line.file_descriptor, &bytes);
         }
 
         fn log_as_tree(&mut self, dir_entries: Vec<(String, bool)>, preceding_str: Option<String>) -> Vec<String> {
             let result = Vec::<String>::new();
             for entry in dir_entries {
                 if entry.1 {
-                    println!("{}{}", preceding_str.clone().unwrap_or(String::new()), entry.0.green())
+                    println!("{}{}", preceding_str.clone().unwrap_or_default(), entry.0.green())
                 }
                 else {
-                    println!("{}{}", preceding_str.clone().unwrap_or(String::new()), entry.0.red())
+                    println!("{}{}", preceding_str.clone().unwrap_or_default(), entry.0.red())
                 }
             }
             result
         }
     }
+
+    /// Writes the bytes as they are, which do not have to be UTF-8.
+    pub fn write_to_file_descriptor(file_descriptor: Option<FileDescriptor>, bytes: &[u8]) {
+        match file_descriptor {
+            // nothing is left to write to once stdout or stderr are closed, e.g. by `| head`
+            Some(FileDescriptor::StdOut) | None => {
+                _ = std::io::stdout().lock().write_all(bytes);
+            }
+            Some(FileDescriptor::StdErr) => {
+                _ = std::io::stderr().lock().write_all(bytes);
+            }
+            Some(fd) => {
+                // the file is owned by whoever opened it, so it must not be closed here
+                let mut f = ManuallyDrop::new(unsafe { File::from_raw_fd(fd.as_raw_fd()) });
+                if let Err(error) = f.write_all(bytes) {
+                    eprintln!("rfind: failed to write to file descriptor {}: {}", fd.as_raw_fd(), error);
+                }
+            }
+        }
+    }
 }
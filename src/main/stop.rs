@@ -0,0 +1,77 @@
+pub use self::stop::{Stop, StopReason};
+pub mod stop {
+    use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+    use std::time::{Duration, Instant};
+
+    // set by the signal handler, which can not do much more than that
+    static SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn handle_signal(_signal: libc::c_int) {
+        SIGNALLED.store(true, Ordering::SeqCst);
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum StopReason {
+        Interrupted,
+        TimedOut,
+        // as many matches were found as were asked for
+        LimitReached
+    }
+
+    /// Whether a search should stop before it has finished. The searcher checks this before every
+    /// entry, so that the jobs left in the threadpool return straight away and whatever was found
+    /// so far is still written.
+    #[derive(Debug, Default)]
+    pub struct Stop {
+        deadline: Option<Instant>,
+        // 0 until the search is stopped, then 1 + the reason
+        reason: AtomicU8
+    }
+
+    impl Stop {
+        pub fn new(timeout: Option<Duration>) -> Stop {
+            Stop {
+                deadline: timeout.map(|timeout| Instant::now() + timeout),
+                reason: AtomicU8::new(0)
+            }
+        }
+
+        /// Stops every search on SIGINT or SIGTERM instead of killing the process. A second signal
+        /// kills it as usual, in case the search does not stop.
+        pub fn catch_signals() {
+            unsafe {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+                action.sa_flags = libc::SA_RESETHAND | libc::SA_RESTART;
+                libc::sigemptyset(&mut action.sa_mask);
+                libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+                libc::sigaction(libc::SIGTERM, &action, std::ptr::null_mut());
+            }
+        }
+
+        pub fn stop(&self, reason: StopReason) {
+            _ = self.reason.compare_exchange(0, reason as u8 + 1, Ordering::SeqCst, Ordering::SeqCst);
+        }
+
+        pub fn reason(&self) -> Option<StopReason> {
+            if self.reason.load(Ordering::Relaxed) == 0 {
+                if SIGNALLED.load(Ordering::Relaxed) {
+                    self.stop(StopReason::Interrupted);
+                }
+                else if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    self.stop(StopReason::TimedOut);
+                }
+            }
+            match self.reason.load(Ordering::SeqCst) {
+                0 => None,
+                1 => Some(StopReason::Interrupted),
+                2 => Some(StopReason::TimedOut),
+                _ => Some(StopReason::LimitReached)
+            }
+        }
+
+        pub fn is_stopped(&self) -> bool {
+            self.reason().is_some()
+        }
+    }
+}
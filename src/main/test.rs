@@ -1,10 +1,34 @@
 pub use self::test::Test;
 pub mod test {
-    use crate::main::*;
+    use std::sync::Arc;
+
+    use crate::main::predicate::{Name, PathRegex, Predicate, True, Types};
+
+    /// A test of the expression. Every test is checked through the predicate it stands for.
     #[derive(Debug, Clone)]
     pub enum Test {
         Name(String),
         Types(String),
-        Regex(String)
+        // matched against the whole path
+        Regex(String),
+        // matches every entry
+        True,
+        // a test that a library searches with, e.g. an expression parsed by a `Registry`
+        Custom(Arc<dyn Predicate>)
+    }
+
+    impl Test {
+        /// The predicate that the search checks, which is made once for the whole search so that
+        /// e.g. a regular expression is only compiled once. Fails when the regular expression does
+        /// not compile.
+        pub fn predicate(&self) -> Result<Arc<dyn Predicate>, String> {
+            Ok(match self {
+                Test::Name(name) => Arc::new(Name(name.clone())),
+                Test::Types(types) => Arc::new(Types(types.clone())),
+                Test::Regex(regex) => Arc::new(PathRegex::new(regex)?),
+                Test::True => Arc::new(True),
+                Test::Custom(predicate) => Arc::clone(predicate)
+            })
+        }
     }
 }
@@ -4,6 +4,7 @@ pub mod testlogger {
     use crate::main::line::Line;
     use crate::main::filedescriptor::FileDescriptor;
     use crate::main::logger::Logger;
+    #[derive(Default)]
     pub struct TestLogger {
         logs: Vec<Line>
     }
@@ -17,7 +18,7 @@ pub mod testlogger {
 
         pub fn print(&self) {
             let logs = &self.logs;
-            let logs_iter = logs.into_iter();
+            let logs_iter = logs.iter();
             for log in logs_iter {
                 let b = log.message.clone();
                 match b {
@@ -30,7 +31,7 @@ pub mod testlogger {
 
         pub fn get_logs(&self) -> Vec<&Line> {
             let logs = &self.logs;
-            let logs_iter = logs.into_iter();
+            let logs_iter = logs.iter();
             logs_iter.filter(|_| {
                 true
             }).collect()
@@ -38,7 +39,7 @@ pub mod testlogger {
 
         pub fn get_logs_by_file_descriptor(&self, file_descriptor: FileDescriptor) -> Vec<&Line> {
             let logs = &self.logs;
-            let logs_iter = logs.into_iter();
+            let logs_iter = logs.iter();
             logs_iter.filter(move |&x| {
                 x.file_descriptor == Some(file_descriptor)
             }).collect()
@@ -64,10 +65,10 @@ pub mod testlogger {
             let result = Vec::<String>::new();
             for entry in dir_entries {
                 if entry.1 {
-                    println!("{}{}", preceding_str.clone().unwrap_or(String::new()), entry.0.green())
+                    println!("{}{}", preceding_str.clone().unwrap_or_default(), entry.0.green())
                 }
                 else {
-                    println!("{}{}", preceding_str.clone().unwrap_or(String::new()), entry.0.red())
+                    println!("{}{}", preceding_str.clone().unwrap_or_default(), entry.0.red())
                 }
             }
             result
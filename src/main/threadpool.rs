@@ -1,59 +1,193 @@
 pub use self::threadpool::ThreadPool;
 pub mod threadpool {
-    use std::sync::mpsc;
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+    use std::fmt;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::thread;
+    use crate::main::job::Job;
     use crate::main::worker::Worker;
-    use crate::main::multithreadmessage::MultiThreadMessage;
-    use std::sync::{Arc, Mutex};
+
+    thread_local! {
+        // the pool and index of the worker running on this thread, if any
+        static CURRENT_WORKER: Cell<Option<(*const Shared, usize)>> = const { Cell::new(None) };
+    }
+
+    /// State shared by the pool and its workers. Every worker has its own deque: it pushes and pops
+    /// jobs at the back, and idle workers steal from the front of the others. Jobs that are submitted
+    /// from outside of the pool go through the injector.
+    pub struct Shared {
+        injector: Mutex<VecDeque<Job>>,
+        deques: Vec<Mutex<VecDeque<Job>>>,
+        // jobs waiting in a deque or the injector
+        queued: AtomicUsize,
+        // jobs that have been submitted but have not finished yet
+        outstanding: AtomicUsize,
+        sleepers: AtomicUsize,
+        sleep_lock: Mutex<()>,
+        wake: Condvar,
+        done_lock: Mutex<()>,
+        done: Condvar,
+        // jobs that panicked, which does not stop the worker that ran them
+        panicked: AtomicUsize,
+        shutdown: AtomicBool
+    }
+
+    impl fmt::Debug for Shared {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Shared")
+                .field("queued", &self.queued)
+                .field("outstanding", &self.outstanding)
+                .field("shutdown", &self.shutdown)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl Shared {
+        pub fn find_job(&self, id: usize) -> Option<Job> {
+            // only one queue is locked at a time, so that two workers stealing from each other can not deadlock
+            let mut job = self.deques[id].lock().unwrap().pop_back();
+            if job.is_none() {
+                job = self.injector.lock().unwrap().pop_front();
+            }
+            let count = self.deques.len();
+            for offset in 1..count {
+                if job.is_some() {
+                    break;
+                }
+                job = self.deques[(id + offset) % count].lock().unwrap().pop_front();
+            }
+            if job.is_some() {
+                self.queued.fetch_sub(1, Ordering::SeqCst);
+            }
+            job
+        }
+
+        pub fn run(&self, id: usize, job: Job) {
+            // decrements the outstanding count even if the job panics
+            struct Finished<'a>(&'a Shared);
+            impl Drop for Finished<'_> {
+                fn drop(&mut self) {
+                    if self.0.outstanding.fetch_sub(1, Ordering::SeqCst) == 1 {
+                        let _guard = self.0.done_lock.lock().unwrap();
+                        self.0.done.notify_all();
+                    }
+                }
+            }
+            let _finished = Finished(self);
+            CURRENT_WORKER.with(|current| current.set(Some((self as *const Shared, id))));
+            if panic::catch_unwind(AssertUnwindSafe(move || job.call_box())).is_err() {
+                self.panicked.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        /// Blocks until there is a job to run or the pool is shutting down.
+        pub fn sleep(&self) {
+            let guard = self.sleep_lock.lock().unwrap();
+            self.sleepers.fetch_add(1, Ordering::SeqCst);
+            if self.queued.load(Ordering::SeqCst) == 0 && !self.is_shutting_down() {
+                drop(self.wake.wait(guard).unwrap());
+            }
+            self.sleepers.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        pub fn is_shutting_down(&self) -> bool {
+            self.shutdown.load(Ordering::SeqCst)
+        }
+    }
 
     #[derive(Debug)]
     pub struct ThreadPool {
         workers: Vec<Worker>,
-        sender: mpsc::Sender<MultiThreadMessage>,
+        shared: Arc<Shared>
     }
 
     impl ThreadPool {
         pub fn new(size: usize) -> ThreadPool {
             assert!(size > 0);
 
-            let (sender, receiver) = mpsc::channel();
-
-            let receiver = Arc::new(Mutex::new(receiver));
+            let shared = Arc::new(Shared {
+                injector: Mutex::new(VecDeque::new()),
+                deques: (0..size).map(|_| Mutex::new(VecDeque::new())).collect(),
+                queued: AtomicUsize::new(0),
+                outstanding: AtomicUsize::new(0),
+                sleepers: AtomicUsize::new(0),
+                sleep_lock: Mutex::new(()),
+                wake: Condvar::new(),
+                done_lock: Mutex::new(()),
+                done: Condvar::new(),
+                panicked: AtomicUsize::new(0),
+                shutdown: AtomicBool::new(false)
+            });
 
             let mut workers = Vec::with_capacity(size);
 
             for id in 0..size {
-                workers.push(Worker::new(id, Arc::clone(&receiver)));
+                workers.push(Worker::new(id, Arc::clone(&shared)));
             }
             ThreadPool {
                 workers,
-                sender,
+                shared
             }
         }
+
+        pub fn size(&self) -> usize {
+            self.workers.len()
+        }
+
+        /// The number of jobs that panicked.
+        pub fn panicked(&self) -> usize {
+            self.shared.panicked.load(Ordering::SeqCst)
+        }
+
+        /// Queues a job. Jobs that are queued by a job running on this pool go to the deque of the
+        /// worker that runs it, so that a worker keeps working on the part of the tree it is in.
         pub fn execute<F>(&self, f: F)
             where
                 F: FnOnce() + Send + 'static
         {
-            let job = Box::new(f);
+            let job: Job = Box::new(f);
+            let shared = &self.shared;
+            shared.outstanding.fetch_add(1, Ordering::SeqCst);
+            // counted before it is pushed, since a worker can steal and uncount it as soon as it is
+            // there, which would otherwise wrap the count around
+            shared.queued.fetch_add(1, Ordering::SeqCst);
+            let current_worker = CURRENT_WORKER.with(|current| current.get());
+            match current_worker {
+                Some((pool, id)) if std::ptr::eq(pool, Arc::as_ptr(shared)) => shared.deques[id].lock().unwrap().push_back(job),
+                _ => shared.injector.lock().unwrap().push_back(job)
+            }
+            if shared.sleepers.load(Ordering::SeqCst) > 0 {
+                let _guard = shared.sleep_lock.lock().unwrap();
+                shared.wake.notify_one();
+            }
+        }
 
-            self.sender.send(MultiThreadMessage::NewJob(job)).unwrap()
+        /// Blocks until every job has finished, including the jobs that were queued by other jobs.
+        pub fn wait(&self) {
+            let mut guard = self.shared.done_lock.lock().unwrap();
+            while self.shared.outstanding.load(Ordering::SeqCst) > 0 {
+                guard = self.shared.done.wait(guard).unwrap();
+            }
         }
     }
 
     impl Drop for ThreadPool {
         fn drop(&mut self) {
-            println!("Sending terminate message to all workers.");
-
-            for _ in &mut self.workers {
-                self.sender.send(MultiThreadMessage::Terminate).unwrap();
+            self.shared.shutdown.store(true, Ordering::SeqCst);
+            {
+                let _guard = self.shared.sleep_lock.lock().unwrap();
+                self.shared.wake.notify_all();
             }
 
-            println!("Shutting down all workers.");
-
             for worker in &mut self.workers {
-                println!("Shutting down worker {}", worker.id);
-
                 if let Some(thread) = worker.thread.take() {
-                    thread.join().unwrap();
+                    // a worker can not wait for itself to finish
+                    if thread.thread().id() != thread::current().id() {
+                        _ = thread.join();
+                    }
                 }
             }
         }
@@ -1,9 +1,8 @@
 pub use self::worker::Worker;
 pub mod worker {
-    use std::sync::mpsc;
     use std::thread;
-    use crate::main::multithreadmessage::MultiThreadMessage;
-    use std::sync::{Arc, Mutex};
+    use crate::main::threadpool::threadpool::Shared;
+    use std::sync::Arc;
     #[derive(Debug)]
     pub struct Worker {
         pub id: usize,
@@ -11,20 +10,13 @@ pub mod worker {
     }
 
     impl Worker {
-        pub fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<MultiThreadMessage>>>) -> Worker {
+        pub fn new(id: usize, shared: Arc<Shared>) -> Worker {
             let thread = thread::spawn(move || {
                 loop {
-                    let message = receiver.lock().unwrap().recv().unwrap();
-
-                    match message {
-                        MultiThreadMessage::NewJob(job) => {
-                            println!("Worker {} got a job; executing.", id);
-                            job.call_box();
-                        },
-                        MultiThreadMessage::Terminate => {
-                            println!("Worker {} was told to terminate", id);
-                            break;
-                        }
+                    match shared.find_job(id) {
+                        Some(job) => shared.run(id, job),
+                        None if shared.is_shutting_down() => break,
+                        None => shared.sleep()
                     }
                 }
             });
@@ -1,6 +1,3 @@
-#![feature(test)]
-#![feature(rustc_private)]
-
 #[cfg(test)]
 mod tests {
 
@@ -49,7 +46,6 @@ mod tests {
     #[test]
     fn cli_get_file_by_type_when_type_of_file_is_file() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        assert!(std::env::set_current_dir(STARTING_PATH_STR).is_ok());
         assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
         assert_fs::TempDir::new().unwrap();
         let file = NamedTempFile::new()?;
@@ -71,7 +67,6 @@ mod tests {
     #[test]
     fn cli_get_file_by_type_when_type_of_file_is_dir() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        assert!(std::env::set_current_dir(STARTING_PATH_STR).is_ok());
         assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
         let directory = TempDir::new()?;
         let directory_path = directory.path().to_str().unwrap();
@@ -92,13 +87,10 @@ mod tests {
     #[test]
     fn cli_get_file_by_type_when_type_of_file_is_symlink_and_symlink_setting_is_not_set_to_follow() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        assert!(std::env::set_current_dir(STARTING_PATH_STR).is_ok());
         assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
 
         let current_directory = TempDir::new()?;
         let directory_of_link = TempDir::new()?;
-        let working_directory_before_test = std::env::current_dir().unwrap();
-        assert!(std::env::set_current_dir(current_directory.path()).is_ok());
 
         let original_file_path = current_directory.path().join("get_file_by_type_when_type_of_file_is_symlink_and_symlink_setting_is_not_set_to_follow.txt");
         let original_file = File::create(original_file_path.clone())?;
@@ -117,7 +109,6 @@ mod tests {
         // Teardown
         current_directory.close()?;
         directory_of_link.close()?;
-        let _ = std::env::set_current_dir(working_directory_before_test)?;
         drop(original_file);
         Ok(())
     }
@@ -125,13 +116,10 @@ mod tests {
     #[test]
     fn cli_do_not_get_file_by_type_when_type_of_file_is_symlink_and_symlink_setting_is_set_to_follow() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        assert!(std::env::set_current_dir(STARTING_PATH_STR).is_ok());
         assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
 
         let current_directory = TempDir::new()?;
         let directory_of_link = TempDir::new()?;
-        let working_directory_before_test = std::env::current_dir().unwrap();
-        assert!(std::env::set_current_dir(current_directory.path()).is_ok());
 
         let original_file_path = current_directory.path().join("do_not_get_file_by_type_when_type_of_file_is_symlink_and_symlink_setting_is_set_to_follow.txt");
         let original_file = File::create(original_file_path.clone())?;
@@ -150,7 +138,6 @@ mod tests {
         // Teardown
         current_directory.close()?;
         directory_of_link.close()?;
-        let _ = std::env::set_current_dir(working_directory_before_test)?;
         drop(original_file);
         Ok(())
     }
@@ -158,7 +145,6 @@ mod tests {
     #[test]
     fn cli_get_file_when_multiple_types_are_provided() -> Result<(), Box<dyn std::error::Error>> {
         // Assert
-        assert!(std::env::set_current_dir(STARTING_PATH_STR).is_ok());
         assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
 
         let file = NamedTempFile::new()?;
@@ -180,7 +166,6 @@ mod tests {
     #[test]
     fn cli_find_file_in_same_directory() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        assert!(std::env::set_current_dir(STARTING_PATH_STR).is_ok());
         assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
 
         let file = NamedTempFile::new()?;
@@ -202,7 +187,6 @@ mod tests {
     #[test]
     fn cli_find_file_in_child_directory() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange
-        assert!(std::env::set_current_dir(STARTING_PATH_STR).is_ok());
         assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
 
         // Create a directory inside of `env::temp_dir()`
@@ -231,7 +215,6 @@ mod tests {
     #[test]
     fn cli_does_not_find_file_in_child_directory_when_max_depth_is_set_to_zero() -> Result<(), Box<dyn std::error::Error>> {
         // Arrange 
-        assert!(std::env::set_current_dir(STARTING_PATH_STR).is_ok());
         assert_eq!(tempfile::env::temp_dir(), std::env::temp_dir());
 
         // Create a directory inside of `env::temp_dir()`
@@ -258,27 +241,718 @@ mod tests {
         // Arrange
         let current_directory = TempDir::new()?;
         let directory_of_link = TempDir::new()?;
-        let working_directory_before_test = std::env::current_dir().unwrap();
-        assert!(std::env::set_current_dir(current_directory.path()).is_ok());
 
-        let original_file_path = current_directory.path().join("does_not_follow_symbolic_links_by_default.txt");
+        let original_file_path = current_directory.path().join("cli_follows_symlink_when_set_to_follow.txt");
         let original_file = File::create(original_file_path.clone())?;
 
         let directory_of_link_path = directory_of_link.path().join("symlink");
-        std::os::unix::fs::symlink(&original_file_path, directory_of_link_path.clone())?;
+        std::os::unix::fs::symlink(current_directory.path(), directory_of_link_path.clone())?;
 
         // Act
         let mut cmd = Command::cargo_bin("main")?;
-        cmd.arg("-L").arg(STARTING_PATH_STR).arg("--").arg("--name").arg("symlink");
+        cmd.arg("-L").arg(directory_of_link.path()).arg("--").arg("--name").arg("cli_follows_symlink_when_set_to_follow.txt");
 
         // Assert
-        cmd.assert().success().stdout(predicate::str::contains(directory_of_link_path.to_str().unwrap()).not());
+        let expected = format!("{}\n", directory_of_link_path.join("cli_follows_symlink_when_set_to_follow.txt").to_str().unwrap());
+        cmd.assert().success().stdout(expected);
 
         // Teardown
         current_directory.close()?;
         directory_of_link.close()?;
-        let _ = std::env::set_current_dir(working_directory_before_test)?;
         drop(original_file);
         Ok(())
     }
+
+    #[test]
+    fn cli_reports_a_file_system_loop_when_following_symlinks() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("a/b/cli_reports_a_file_system_loop.txt").touch()?;
+        let link = temp.child("a/b/up");
+        link.symlink_to_dir(temp.child("a").path())?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("-L").arg(temp.path()).arg("--").arg("--name").arg("cli_reports_a_file_system_loop.txt");
+
+        // Assert
+        let expected = format!("{}\n", temp.child("a/b/cli_reports_a_file_system_loop.txt").path().to_str().unwrap());
+        cmd.assert().failure().stdout(expected).stderr(predicate::str::contains("File system loop detected").and(predicate::str::contains(link.path().to_str().unwrap())));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_error_summary_counts_errors_by_kind_and_exits_with_1() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("a/b/cli_error_summary.txt").touch()?;
+        temp.child("a/b/up").symlink_to_dir(temp.child("a").path())?;
+        temp.child("c/cli_error_summary.txt").touch()?;
+
+        // Act
+        let mut failing = Command::cargo_bin("main")?;
+        failing.arg("-L").arg("--error-summary").arg(temp.child("a").path()).arg("--").arg("--name").arg("cli_error_summary.txt");
+        let mut succeeding = Command::cargo_bin("main")?;
+        succeeding.arg("--error-summary").arg(temp.child("c").path()).arg("--").arg("--name").arg("cli_error_summary.txt");
+
+        // Assert
+        failing.assert().code(1).stderr(predicate::str::contains("rfind: 1 errors: 0 permission denied, 0 vanished, 1 file system loops, 0 I/O"));
+        succeeding.assert().code(0).stderr("rfind: 0 errors: 0 permission denied, 0 vanished, 0 file system loops, 0 I/O\n");
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_a_missing_starting_point_fails_even_when_ignoring_readdir_races() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        let mut cmd = Command::cargo_bin("main")?;
+
+        // Act
+        cmd.arg("--ignore_readdir_race").arg(temp.child("missing").path()).arg("--").arg("--type").arg("f");
+
+        // Assert
+        cmd.assert().code(1).stderr(predicate::str::contains("missing"));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_follows_a_symlink_that_is_a_starting_point_when_set_to_only_command_line_args() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("directory/cli_follows_a_starting_point.txt").touch()?;
+        let link = temp.child("link");
+        link.symlink_to_dir(temp.child("directory").path())?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("-H").arg(link.path()).arg("--").arg("--name").arg("cli_follows_a_starting_point.txt");
+        let mut cmd_never = Command::cargo_bin("main")?;
+        cmd_never.arg("-P").arg(link.path()).arg("--").arg("--name").arg("cli_follows_a_starting_point.txt");
+
+        // Assert
+        let expected = format!("{}\n", link.child("cli_follows_a_starting_point.txt").path().to_str().unwrap());
+        cmd.assert().success().stdout(expected);
+        cmd_never.assert().success().stdout("");
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_does_not_follow_symlinks_below_the_starting_point_when_set_to_only_command_line_args() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("directory/cli_does_not_follow_symlinks_below.txt").touch()?;
+        temp.child("starting_point").create_dir_all()?;
+        temp.child("starting_point/link").symlink_to_dir(temp.child("directory").path())?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("-H").arg(temp.child("starting_point").path()).arg("--").arg("--name").arg("cli_does_not_follow_symlinks_below.txt");
+
+        // Assert
+        cmd.assert().success().stdout("");
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_delete_refuses_to_run_with_prune() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        let input_file = temp.child("cli_delete_refuses_to_run_with_prune.txt");
+        input_file.touch()?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg(temp.path()).arg("--").arg("--name").arg("cli_delete_refuses_to_run_with_prune.txt").arg("--prune").arg("--delete");
+
+        // Assert
+        cmd.assert().failure().stderr(predicate::str::contains("--prune"));
+        input_file.assert(predicate::path::exists());
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_rejects_a_regex_that_does_not_compile() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("cli_rejects_a_regex.txt").touch()?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg(temp.path()).arg("--").arg("--regex").arg("*.txt");
+
+        // Assert
+        cmd.assert().failure().stdout("").stderr(predicate::str::contains("invalid regular expression *.txt"));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_regex_matches_the_whole_path() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("src/cli_regex.rs").touch()?;
+        temp.child("cli_regex.rs").touch()?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg(temp.path()).arg("--").arg("--regex").arg(".*/src/.*\\.rs");
+        let mut name_only = Command::cargo_bin("main")?;
+        name_only.arg(temp.path()).arg("--").arg("--regex").arg("cli_regex\\.rs");
+
+        // Assert
+        cmd.assert().success().stdout(format!("{}\n", temp.child("src/cli_regex.rs").path().to_string_lossy()));
+        name_only.assert().success().stdout("");
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_expression_operators_run_one_search() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("a.txt").touch()?;
+        temp.child("b.rs").touch()?;
+        temp.child("c.md").touch()?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg(temp.path()).arg("--").args(["--type", "f", "--not", "(", "--name", "a.txt", "--or", "--name", "b.rs", ")"]);
+        let mut unknown = Command::cargo_bin("main")?;
+        unknown.arg(temp.path()).arg("--").arg("--size");
+
+        // Assert
+        cmd.assert().success().stdout(format!("{}\n", temp.child("c.md").path().to_string_lossy()));
+        unknown.assert().failure().stderr("rfind: unknown test --size\n");
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_delete_without_a_test_removes_everything_below_the_starting_point() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        for path in ["1.txt", "a/2.txt", "a/b/3.txt"] {
+            temp.child(path).touch()?;
+        }
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg(temp.child("a").path()).arg("--").arg("--delete");
+
+        // Assert
+        cmd.assert().success().stdout("").stderr("");
+        temp.child("a").assert(predicate::path::missing());
+        temp.child("1.txt").assert(predicate::path::exists());
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_ls_lists_symlinks_with_their_target() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("original.txt").write_str("some data")?;
+        let link = temp.child("cli_ls_lists_symlinks_with_their_target");
+        link.symlink_to_file(temp.child("original.txt").path())?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg(temp.path()).arg("--").arg("--name").arg("cli_ls_lists_symlinks_with_their_target").arg("--ls");
+
+        // Assert
+        let expected = "lrwxrwxrwx   1 ";
+        let target = format!("{} -> {}", link.path().to_str().unwrap(), temp.child("original.txt").path().to_str().unwrap());
+        cmd.assert().success().stdout(predicate::str::contains(expected).and(predicate::str::contains(target)));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fls_writes_the_listing_to_a_file() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("cli_fls_writes_the_listing_to_a_file.txt").write_str("some data")?;
+        let output = assert_fs::NamedTempFile::new("listing.txt")?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg(temp.path()).arg("--").arg("--name").arg("cli_fls_writes_the_listing_to_a_file.txt").arg("--fls").arg(output.path());
+
+        // Assert
+        cmd.assert().success();
+        output.assert(predicate::str::contains("-rw-").and(predicate::str::contains("        9 ")).and(predicate::str::contains("cli_fls_writes_the_listing_to_a_file.txt")));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fprint_and_fprintf_share_a_file_that_is_named_twice() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        let input_file = temp.child("cli_fprint_and_fprintf_share_a_file_that_is_named_twice.txt");
+        input_file.write_str("some data")?;
+        let report = assert_fs::NamedTempFile::new("report.txt")?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg(temp.path()).arg("--").arg("--name").arg("cli_fprint_and_fprintf_share_a_file_that_is_named_twice.txt")
+            .arg("--fprint").arg(report.path())
+            .arg("--fprintf").arg(report.path()).arg("%f has size %s\\n");
+
+        // Assert
+        cmd.assert().success();
+        let expected = format!("{}\ncli_fprint_and_fprintf_share_a_file_that_is_named_twice.txt has size 9\n", input_file.path().to_str().unwrap());
+        report.assert(expected);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_printf_octal_escapes_are_single_bytes() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("cli_printf_octal_escapes.txt").touch()?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg(temp.path()).arg("--").arg("--name").arg("cli_printf_octal_escapes.txt").arg("--printf").arg("\\377%f\\0");
+
+        // Assert
+        cmd.assert().success().stdout([b"\xff".as_slice(), b"cli_printf_octal_escapes.txt\0"].concat());
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_fprint0_terminates_paths_with_nul() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        let input_file = temp.child("cli_fprint0_terminates_paths_with_nul.txt");
+        input_file.touch()?;
+        let report = assert_fs::NamedTempFile::new("report.txt")?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg(temp.path()).arg("--").arg("--name").arg("cli_fprint0_terminates_paths_with_nul.txt").arg("--fprint0").arg(report.path());
+
+        // Assert
+        cmd.assert().success();
+        report.assert(format!("{}\0", input_file.path().to_str().unwrap()));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_print0_output_can_be_read_back_by_files0_from_when_a_name_is_not_utf8() -> Result<(), Box<dyn std::error::Error>> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        // Arrange
+        // not in /tmp, which other tests search and expect to be UTF-8
+        let temp = TempDir::new_in(env!("CARGO_TARGET_TMPDIR"))?;
+        let directory = temp.path().join(OsStr::from_bytes(b"not_utf8_\xff"));
+        std::fs::create_dir(&directory)?;
+        std::fs::write(directory.join("cli_print0_round_trip.txt"), "")?;
+
+        // Act
+        let mut print0 = Command::cargo_bin("main")?;
+        print0.arg(temp.path()).arg("--").arg("--mindepth").arg("1").arg("--type").arg("d").arg("--print0");
+        let starting_points = print0.assert().success().get_output().stdout.clone();
+        let mut files0_from = assert_cmd::Command::cargo_bin("main")?;
+        files0_from.arg("--files0-from").arg("-").arg("--").arg("--name").arg("cli_print0_round_trip.txt").arg("--print0")
+            .write_stdin(starting_points.clone());
+
+        // Assert
+        assert_eq!(starting_points, [directory.as_os_str().as_bytes(), b"\0"].concat());
+        files0_from.assert().success().stdout([directory.join("cli_print0_round_trip.txt").as_os_str().as_bytes(), b"\0"].concat());
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_json_format_writes_an_object_per_match_and_per_error() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        let input_file = temp.child("cli_json_format_writes_an_object_per_match.txt");
+        input_file.write_str("some data")?;
+        let missing_directory = temp.child("missing_directory");
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("--format").arg("json").arg(temp.path()).arg("--").arg("--name").arg("cli_json_format_writes_an_object_per_match.txt");
+        let mut cmd_for_error = Command::cargo_bin("main")?;
+        cmd_for_error.arg("--format").arg("json").arg(missing_directory.path()).arg("--").arg("--name").arg("some_file.txt");
+
+        // Assert
+        let expected = format!("{{\"type\":\"match\",\"path\":\"{}\",\"name\":\"cli_json_format_writes_an_object_per_match.txt\",\"depth\":1,\"file_type\":\"file\",\"size\":9,", input_file.path().to_str().unwrap());
+        cmd.assert().success().stdout(predicate::str::starts_with(expected));
+        cmd_for_error.assert().stdout(predicate::str::starts_with("{\"type\":\"error\",\"message\":\"rfind: ").and(predicate::str::contains("missing_directory")));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_json_format_gives_the_bytes_of_a_path_that_is_not_utf8() -> Result<(), Box<dyn std::error::Error>> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        // Arrange
+        // not in /tmp, which other tests search and expect to be UTF-8
+        let temp = TempDir::new_in(env!("CARGO_TARGET_TMPDIR"))?;
+        let input_file = temp.path().join(OsStr::from_bytes(b"cli_json_\xff.txt"));
+        std::fs::write(&input_file, "")?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("--format").arg("json").arg(temp.path()).arg("--").arg("--type").arg("f");
+
+        // Assert
+        let path_bytes: Vec<String> = input_file.as_os_str().as_bytes().iter().map(|byte| byte.to_string()).collect();
+        let expected = format!("{{\"type\":\"match\",\"path\":\"{}\",\"path_bytes\":[{}],\"name\":\"cli_json_\u{fffd}.txt\",",
+            input_file.to_string_lossy(), path_bytes.join(","));
+        cmd.assert().success().stdout(predicate::str::starts_with(expected));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_csv_format_quotes_values_and_writes_a_header() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        let input_file = temp.child("cli_csv_format,\"quotes\".txt");
+        input_file.write_str("some data")?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("--format").arg("csv").arg("--columns").arg("name,size,u").arg("--header").arg(temp.path())
+            .arg("--").arg("--name").arg("cli_csv_format,\"quotes\".txt");
+
+        // Assert
+        let owner = String::from_utf8(std::process::Command::new("id").arg("-un").output()?.stdout)?;
+        let expected = format!("name,size,owner\r\n\"cli_csv_format,\"\"quotes\"\".txt\",9,{}\r\n", owner.trim());
+        cmd.assert().success().stdout(expected);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_csv_header_is_written_when_nothing_matches() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("--format").arg("tsv").arg("--columns").arg("path,size").arg("--header").arg(temp.path())
+            .arg("--").arg("--name").arg("cli_csv_header_no_matches.txt");
+
+        // Assert
+        cmd.assert().success().stdout("path\tsize\n");
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_csv_header_is_not_written_by_exists() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("cli_csv_header_exists.txt").touch()?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("--format").arg("csv").arg("--columns").arg("name").arg("--header").arg("--exists").arg(temp.path())
+            .arg("--").arg("--name").arg("cli_csv_header_exists.txt");
+
+        // Assert
+        cmd.assert().code(0).stdout("");
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_single_thread_and_rfind_threads_find_the_same_files() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        let input_file = temp.child("a/b/c/cli_single_thread.txt");
+        input_file.touch()?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("-j").arg("1").arg(temp.path()).arg("--").arg("--name").arg("cli_single_thread.txt");
+        let mut cmd_with_env = Command::cargo_bin("main")?;
+        cmd_with_env.env("RFIND_THREADS", "2").arg(temp.path()).arg("--").arg("--name").arg("cli_single_thread.txt");
+        let mut cmd_with_zero_threads = Command::cargo_bin("main")?;
+        cmd_with_zero_threads.arg("-j").arg("0").arg(temp.path());
+
+        // Assert
+        let expected = format!("{}\n", input_file.path().to_str().unwrap());
+        cmd.assert().success().stdout(expected.clone());
+        cmd_with_env.assert().success().stdout(expected);
+        cmd_with_zero_threads.assert().failure();
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_sort_writes_matches_in_reverse_order_of_size() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        let small_file = temp.child("a/cli_sort.txt");
+        small_file.write_str("1")?;
+        let large_file = temp.child("b/cli_sort.txt");
+        large_file.write_str("12345")?;
+        let medium_file = temp.child("c/d/cli_sort.txt");
+        medium_file.write_str("123")?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("--sort").arg("size").arg("--reverse").arg(temp.path()).arg("--").arg("--name").arg("cli_sort.txt");
+
+        // Assert
+        let expected = format!("{}\n{}\n{}\n", large_file.path().to_str().unwrap(), medium_file.path().to_str().unwrap(), small_file.path().to_str().unwrap());
+        cmd.assert().success().stdout(expected);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_searches_every_starting_point_with_the_same_expression() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        let first_file = temp.child("first/cli_every_starting_point.txt");
+        first_file.touch()?;
+        let second_file = temp.child("second/nested/cli_every_starting_point.txt");
+        second_file.touch()?;
+        temp.child("third/cli_every_starting_point.txt").touch()?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("-j").arg("1").arg(temp.child("first").path()).arg(temp.child("second").path())
+            .arg("--").arg("--name").arg("cli_every_starting_point.txt").arg("--printf").arg("%P %p\\n");
+
+        // Assert
+        let expected = format!("cli_every_starting_point.txt {}\nnested/cli_every_starting_point.txt {}\n", first_file.path().to_str().unwrap(), second_file.path().to_str().unwrap());
+        cmd.assert().success().stdout(expected);
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_reads_starting_points_separated_by_nul_from_a_file() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        let first_file = temp.child("first/cli_files0_from.txt");
+        first_file.touch()?;
+        let second_file = temp.child("second/cli_files0_from.txt");
+        second_file.touch()?;
+        let files0_from = temp.child("starting_points");
+        files0_from.write_str(&format!("{}\0{}\0", temp.child("second").path().to_str().unwrap(), temp.child("first").path().to_str().unwrap()))?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("--files0-from").arg(files0_from.path()).arg("--").arg("--name").arg("cli_files0_from.txt");
+        let mut cmd_from_stdin = assert_cmd::Command::cargo_bin("main")?;
+        cmd_from_stdin.arg("--files0-from").arg("-").arg("--").arg("--name").arg("cli_files0_from.txt")
+            .write_stdin(format!("{}\0\0", temp.child("first").path().to_str().unwrap()));
+
+        // Assert
+        let expected = format!("{}\n{}\n", second_file.path().to_str().unwrap(), first_file.path().to_str().unwrap());
+        cmd.assert().success().stdout(expected);
+        cmd_from_stdin.assert().failure().stderr(predicate::str::contains("invalid zero-length file name"));
+
+        // Teardown
+        Ok(())
+    }
+
+    // the signals that a process catches are listed in hex as SigCgt in /proc/<pid>/status
+    fn wait_until_catching_sigint(pid: u32) -> std::io::Result<()> {
+        loop {
+            let status = std::fs::read_to_string(format!("/proc/{}/status", pid))?;
+            let caught = status.lines()
+                .find_map(|line| line.strip_prefix("SigCgt:"))
+                .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+                .unwrap_or(0);
+            if caught & (1 << (libc::SIGINT - 1)) != 0 {
+                return Ok(());
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn cli_exits_with_130_when_interrupted() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("cli_exits_with_130.txt").touch()?;
+        // blocks reading the starting points from stdin until it is closed, by which time it has been interrupted
+        let mut child = Command::cargo_bin("main")?
+            .arg("--files0-from").arg("-").arg("--").arg("--name").arg("cli_exits_with_130.txt")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        wait_until_catching_sigint(child.id())?;
+
+        // Act
+        unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGINT) };
+        std::io::Write::write_all(&mut stdin, temp.path().to_str().unwrap().as_bytes())?;
+        drop(stdin);
+        let output = child.wait_with_output()?;
+
+        // Assert
+        assert_eq!(output.status.code(), Some(130));
+        assert!(output.stdout.is_empty());
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_max_results_and_exists_stop_at_the_first_matches() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        for i in 0..100 {
+            temp.child(format!("{}/cli_max_results.txt", i)).touch()?;
+        }
+
+        // Act
+        let output = Command::cargo_bin("main")?
+            .arg("-j").arg("4").arg("--max-results").arg("7").arg(temp.path()).arg("--").arg("--name").arg("cli_max_results.txt")
+            .output()?;
+        let mut exists = Command::cargo_bin("main")?;
+        exists.arg("--exists").arg(temp.path()).arg("--").arg("--name").arg("cli_max_results.txt");
+        let mut does_not_exist = Command::cargo_bin("main")?;
+        does_not_exist.arg("--exists").arg(temp.path()).arg("--").arg("--name").arg("cli_does_not_exist.txt");
+
+        // Assert
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout)?.lines().count(), 7);
+        exists.assert().code(0).stdout("");
+        does_not_exist.assert().code(1).stdout("");
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_exists_refuses_to_run_with_an_action() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("cli_exists_delete.txt").touch()?;
+        let mut delete = Command::cargo_bin("main")?;
+        let mut fprint = Command::cargo_bin("main")?;
+
+        // Act
+        delete.arg("--exists").arg(temp.path()).arg("--").arg("--name").arg("cli_exists_delete.txt").arg("--delete");
+        fprint.arg("--exists").arg(temp.path()).arg("--").arg("--fprint").arg(temp.child("out.txt").path());
+
+        // Assert
+        delete.assert().code(1).stderr(predicate::str::contains("--delete"));
+        fprint.assert().code(1).stderr(predicate::str::contains("--fprint"));
+        temp.child("cli_exists_delete.txt").assert(predicate::path::exists());
+        temp.child("out.txt").assert(predicate::path::missing());
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_bfs_finds_the_shallowest_matches_first() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        for path in ["a/b/c/d/cli_bfs.conf", "a/b/cli_bfs.conf", "e/f/g/cli_bfs.conf", "cli_bfs.conf", "h/cli_bfs.conf"] {
+            temp.child(path).touch()?;
+        }
+
+        // Act
+        let output = Command::cargo_bin("main")?
+            .arg("-j").arg("4").arg("--bfs").arg(temp.path()).arg("--").arg("--name").arg("cli_bfs.conf")
+            .output()?;
+        let mut first = Command::cargo_bin("main")?;
+        first.arg("--bfs").arg("--max-results").arg("1").arg(temp.path()).arg("--").arg("--name").arg("cli_bfs.conf");
+
+        // Assert
+        let stdout = String::from_utf8(output.stdout)?;
+        let depths: Vec<usize> = stdout.lines().map(|line| std::path::Path::new(line).strip_prefix(temp.path()).unwrap().components().count()).collect();
+        assert_eq!(depths, vec![1, 2, 3, 4, 5]);
+        first.assert().success().stdout(format!("{}\n", temp.child("cli_bfs.conf").path().to_str().unwrap()));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_searches_directories_that_match_as_well() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        temp.child("cli_searches_directories_that_match_as_well/cli_searches_directories_that_match_as_well").touch()?;
+
+        // Act
+        let mut cmd = Command::cargo_bin("main")?;
+        cmd.arg("-j").arg("1").arg(temp.path()).arg("--").arg("--name").arg("cli_searches_directories_that_match_as_well");
+
+        // Assert
+        let directory = temp.child("cli_searches_directories_that_match_as_well");
+        let file = directory.child("cli_searches_directories_that_match_as_well");
+        cmd.assert().success().stdout(format!("{}\n{}\n", directory.path().to_str().unwrap(), file.path().to_str().unwrap()));
+
+        // Teardown
+        Ok(())
+    }
+
+    #[test]
+    fn cli_depth_options_can_be_given_anywhere_in_the_expression_with_a_warning_after_tests() -> Result<(), Box<dyn std::error::Error>> {
+        // Arrange
+        let temp = assert_fs::TempDir::new()?;
+        for path in ["1.txt", "a/2.txt", "a/b/3.txt"] {
+            temp.child(path).touch()?;
+        }
+
+        // Act
+        let mut before = Command::cargo_bin("main")?;
+        before.arg(temp.path()).arg("--").arg("--mindepth").arg("2").arg("--maxdepth").arg("2").arg("--type").arg("f");
+        let mut after = Command::cargo_bin("main")?;
+        after.arg(temp.path()).arg("--").arg("--type").arg("f").arg("--maxdepth").arg("1");
+        let mut starting_point = Command::cargo_bin("main")?;
+        starting_point.arg(temp.path()).arg("--").arg("--maxdepth").arg("0").arg("--type").arg("d");
+
+        // Assert
+        before.assert().success().stderr("").stdout(format!("{}\n", temp.child("a/2.txt").path().to_str().unwrap()));
+        after.assert().success()
+            .stderr(predicate::str::contains("rfind: warning: you have specified the global option --maxdepth after the argument --type"))
+            .stdout(format!("{}\n", temp.child("1.txt").path().to_str().unwrap()));
+        starting_point.assert().success().stdout(format!("{}\n", temp.path().to_str().unwrap()));
+
+        // Teardown
+        Ok(())
+    }
 }